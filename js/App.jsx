@@ -10,7 +10,9 @@ export default class App extends Component {
     this.state = {
       mode: "lobby",
       scoring: "china",
+      komi: 0.005,
       flow: "Normal",
+      schedule: "Snake",
       resolution: mobileOrTablet() ? "Blocky" : "Smooth",
       name: "",
       room: "",
@@ -77,6 +79,16 @@ export default class App extends Component {
                   <Option value="japan">Japanese</Option>
                 </Radio>
                 <br className="big" />
+                <Radio
+                  value={this.state.komi}
+                  on={(value) => this.setState({ komi: Number(value) })}
+                >
+                  <legend>Komi for White:</legend>
+                  <Option value={0}>None</Option>
+                  <Option value={0.005}>Half a percent of the board</Option>
+                  <Option value={0.01}>One percent of the board</Option>
+                </Radio>
+                <br className="big" />
                 <Radio
                   value={this.state.flow}
                   on={(value) => this.setState({ flow: value })}
@@ -87,6 +99,16 @@ export default class App extends Component {
                   <Option value={"Fast"}>Fast</Option>
                 </Radio>
                 <br className="big" />
+                <Radio
+                  value={this.state.schedule}
+                  on={(value) => this.setState({ schedule: value })}
+                >
+                  <legend>Turn order:</legend>
+                  <Option value={"Snake"}>Snake</Option>
+                  <Option value={"Alternate"}>Alternating</Option>
+                  <Option value={"Simultaneous"}>Simultaneous</Option>
+                </Radio>
+                <br className="big" />
                 <Radio
                   value={this.state.resolution}
                   on={(value) => this.setState({ resolution: value })}
@@ -133,6 +155,15 @@ export default class App extends Component {
                   touch the edge of the board or the enemy's liquid. (Capture of
                   the enemy takes precedence over self capture.)
                 </li>
+                <li>
+                  Where both players pour into the same space, the liquid takes
+                  turns one cell at a time, and who goes first switches every
+                  frame. With the snake turn order it goes Black, White, White,
+                  Black. With alternating turns it goes Black, White, Black,
+                  White. With simultaneous turns both players pick a cell at
+                  once, and if they pick the same one, they take turns getting
+                  it.
+                </li>
               </ul>
               <h4>End of the game:</h4>
               <ul>
@@ -173,10 +204,14 @@ export default class App extends Component {
         return (
           <Multiplayer
             scoring={this.state.scoring}
+            komi={this.state.komi}
             flow={this.state.flow}
+            schedule={this.state.schedule}
             resolution={this.state.resolution}
             room={this.state.room + "?scoring=" + this.state.scoring +
-              "?flow=" + this.state.flow + "?resolution=" +
+              "?komi=" + this.state.komi +
+              "?flow=" + this.state.flow +
+              "?schedule=" + this.state.schedule + "?resolution=" +
               this.state.resolution}
             thisName={this.state.name || "Stranger"}
           >
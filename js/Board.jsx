@@ -7,9 +7,16 @@ import { memory } from "../pkg/index_bg.wasm";
 export default class Board extends Component {
   static defaultProps = {
     cells: 1200,
+    grid: "Square",
+    komi: 0,
     pixels: 600,
     capturing: true,
     flow: 300,
+    ink: 0,
+    refill: 0,
+    sharing: "Split",
+    schedule: "Snake",
+    whiteWeight: 1,
   };
 
   constructor(props) {
@@ -26,58 +33,125 @@ export default class Board extends Component {
     const offscreen = document.createElement("canvas");
     const ctx = this.canvas.current.getContext("2d");
     const ctxOff = offscreen.getContext("2d");
+    // The ownership heatmap from `estimate`, drawn over the board.
+    const heat = document.createElement("canvas");
+    const ctxHeat = heat.getContext("2d");
     this.canvas.current.width = this.props.pixels;
-    this.canvas.current.height = this.props.pixels;
+    this.canvas.current.height = this.pixelsHigh();
     // this.canvas.current.style.left = Math.round(scrollX + this.canvas.current.getBoundingClientRect().left) + 'px';
     // this.canvas.current.style.top = Math.round(scrollY + this.canvas.current.getBoundingClientRect().top) + 'px';
     // this.canvas.current.style.position = 'absolute';
     offscreen.width = this.props.cells;
-    offscreen.height = this.props.cells;
-
-    this.board = wasm.Board.new(this.props.cells, this.props.capturing);
+    offscreen.height = this.rows();
+    heat.width = this.props.cells;
+    heat.height = this.rows();
+    this.projection = undefined;
+
+    this.board = wasm.Board.with_players(
+      this.props.cells,
+      this.rows(),
+      this.props.capturing,
+      2,
+    );
+    if (this.props.torus) this.board.set_topology(wasm.Topology.Torus);
+    this.board.set_grid(wasm.Grid[this.props.grid]);
+    this.board.set_komi_fraction(1, this.props.komi);
+    this.setInk();
+    this.board.set_sharing(wasm.Sharing[this.props.sharing]);
+    this.board.set_schedule(wasm.Schedule[this.props.schedule]);
+    this.board.set_flow_weight(1, this.props.whiteWeight);
     this.blackMouse = wasm.Point.new(
       this.props.cells / 2,
-      this.props.cells / 2,
+      this.rows() / 2,
     );
     this.whiteMouse = wasm.Point.new(
       this.props.cells / 2,
-      this.props.cells / 2,
+      this.rows() / 2,
     );
     this.mounted = true;
     this.doDraw = true;
     this.blackFirst = true;
     this.mouse = {
       left: false,
+      middle: false,
       right: false,
       x: this.props.cells / 2,
-      y: this.props.cells / 2,
+      y: this.rows() / 2,
+      // Every finger on the touchscreen, in a fixed slot while it's down so that
+      // each one keeps driving the same spigot.
+      fingers: [],
     };
 
-    this.draw = (buffer) => {
-      if (this.props.pixels >= this.props.cells) {
-        this.canvas.current.style.imageRendering = "pixelated";
-        ctx.imageSmoothingEnabled = false;
-      }
-
-      // I'm doing some manual bindings here. This takes care of my vector
-      // transmutation problem and my finicky Rust APIs problem at the same time.
+    // I'm doing some manual bindings here. This takes care of my vector
+    // transmutation problem and my finicky Rust APIs problem at the same time.
+    const copy = (buffer, context) => {
       const slice = this.board.get_image_slice(buffer);
-      ctxOff.putImageData(
+      context.putImageData(
         new ImageData(
           new Uint8ClampedArray(memory.buffer).subarray(
             slice.x,
             slice.x + slice.y,
           ),
           this.props.cells,
-          this.props.cells,
+          this.rows(),
         ),
         0,
         0,
       );
-      ctx.drawImage(offscreen, 0, 0, this.props.pixels, this.props.pixels);
       slice.free();
     };
 
+    const blit = (source) => {
+      if (this.props.grid == "Hex") {
+        // Odd rows of a hex grid sit half a cell to the right.
+        const size = this.props.pixels / this.props.cells;
+        for (let y = 0; y < this.rows(); y++) {
+          ctx.drawImage(
+            source,
+            0,
+            y,
+            this.props.cells,
+            1,
+            y % 2 * size / 2,
+            y * size,
+            this.props.pixels,
+            size,
+          );
+        }
+      } else {
+        ctx.drawImage(source, 0, 0, this.props.pixels, this.pixelsHigh());
+      }
+    };
+
+    this.draw = (buffer) => {
+      if (this.props.pixels >= this.props.cells) {
+        this.canvas.current.style.imageRendering = "pixelated";
+        ctx.imageSmoothingEnabled = false;
+      }
+
+      copy(buffer, ctxOff);
+      ctx.clearRect(0, 0, this.props.pixels, this.pixelsHigh());
+      blit(offscreen);
+      if (buffer == 0 && this.props.estimate && this.projection) blit(heat);
+    };
+
+    // Guess the final score, and redraw the heatmap. The estimate spreads
+    // a tenth of the way across the board, and has to win a cell by a margin.
+    this.updateEstimate = () => {
+      const estimate = this.board.estimate(
+        Math.ceil(this.props.cells / 10),
+        Math.ceil(this.props.cells / 200),
+      );
+      this.projection = {
+        black: estimate.projected(0),
+        white: estimate.projected(1),
+        leader: estimate.leader(),
+      };
+      estimate.free();
+      copy(2, ctxHeat);
+      this.doDraw = true;
+    };
+
     const loop = () => {
       if (!this.mounted) return;
 
@@ -98,6 +172,9 @@ export default class Board extends Component {
         case 0:
           this.mouse.left = true;
           break;
+        case 1:
+          this.mouse.middle = true;
+          break;
         case 2:
           this.mouse.right = true;
           break;
@@ -109,6 +186,9 @@ export default class Board extends Component {
         case 0:
           this.mouse.left = false;
           break;
+        case 1:
+          this.mouse.middle = false;
+          break;
         case 2:
           this.mouse.right = false;
           break;
@@ -131,50 +211,167 @@ export default class Board extends Component {
   }
 
   componentDidUpdate(prevProps) {
-    if (this.props.cells != prevProps.cells) {
+    if (
+      this.props.cells != prevProps.cells ||
+      this.props.rows != prevProps.rows ||
+      this.props.torus != prevProps.torus ||
+      this.props.grid != prevProps.grid ||
+      this.props.komi != prevProps.komi ||
+      this.props.schedule != prevProps.schedule ||
+      this.props.whiteWeight != prevProps.whiteWeight
+    ) {
       this.componentWillUnmount();
       this.componentDidMount();
     } else {
       this.board.set_capturing(this.props.capturing);
+      if (
+        this.props.ink != prevProps.ink || this.props.refill != prevProps.refill
+      ) {
+        this.setInk();
+      }
+      this.board.set_sharing(wasm.Sharing[this.props.sharing]);
     }
 
     if (this.props.pixels != prevProps.pixels) this.draw(0);
   }
 
   componentWillUnmount() {
+    this.lastScore?.free();
+    this.lastScore = undefined;
     this.board.free();
     this.blackMouse.free();
     this.whiteMouse.free();
     this.mounted = false;
   }
 
+  // `black` and `white` are each a sample {x, y, active}, or a list of them for
+  // spilling from several places at once.
   spill(black, white) {
-    this.blackMouse.set(black.x, black.y);
-    this.whiteMouse.set(white.x, white.y);
-
-    const prisoners = this.board.spill(
-      this.blackMouse,
-      black.active,
-      this.whiteMouse,
-      white.active,
-      this.props.flow,
-      this.blackFirst,
-    );
-    const result = { black: prisoners.x, white: prisoners.y };
-    prisoners.free();
+    const blacks = [].concat(black);
+    const whites = [].concat(white);
+    this.blackMouse.set(blacks[0].x, blacks[0].y);
+    this.whiteMouse.set(whites[0].x, whites[0].y);
+
+    const spilled = blacks.length == 1 && whites.length == 1
+      ? this.board.spill(
+        this.blackMouse,
+        blacks[0].active,
+        this.whiteMouse,
+        whites[0].active,
+        this.props.flow,
+        this.blackFirst,
+      )
+      : this.spillTouches(blacks, whites);
+    const result = {
+      black: spilled.b_prisoners,
+      white: spilled.w_prisoners,
+      blackInk: spilled.ink(0),
+      whiteInk: spilled.ink(1),
+    };
+    spilled.free();
 
     this.blackFirst = !this.blackFirst;
     this.doDraw = black.active || white.active;
+    if (this.props.estimate && this.board.frame() % 30 == 0) {
+      this.updateEstimate();
+    }
     return result;
   }
 
+  spillTouches(blacks, whites) {
+    const all = blacks.concat(whites);
+    return this.board.spill_touches(
+      Uint8Array.from(all, (_, i) => i < blacks.length ? 0 : 1),
+      Int32Array.from(all, (sample) => sample.x),
+      Int32Array.from(all, (sample) => sample.y),
+      Uint8Array.from(all, (sample) => sample.active ? 1 : 0),
+      this.props.flow,
+      this.blackFirst ? 0 : 1,
+    );
+  }
+
+  // The fingers on the screen as samples for `spill`, or undefined if there
+  // haven't been more than one at a time.
+  fingers() {
+    if (this.mouse.fingers.length <= 1) return undefined;
+    return this.mouse.fingers.map(({ x, y, active }) => ({ x, y, active }));
+  }
+
+  // Both players get `ink` cells of liquid, and `refill` more every frame. An ink
+  // of 0 means no limit.
+  setInk() {
+    for (const player of [0, 1]) {
+      if (this.props.ink > 0) {
+        this.board.set_ink(player, this.props.ink, this.props.refill);
+      } else {
+        this.board.remove_ink_limit(player);
+      }
+    }
+  }
+
+  // Mark the body at (x, y) dead for scoring, or alive again.
+  toggleDead(x, y) {
+    const point = wasm.Point.new(x, y);
+    const dead = this.board.toggle_dead(point);
+    point.free();
+    return dead;
+  }
+
+  // Put walls on the empty cells within `radius` of (x, y), or take them away.
+  paintWalls(x, y, radius, wall) {
+    const point = wasm.Point.new(0, 0);
+    for (let dy = -radius; dy <= radius; dy++) {
+      for (let dx = -radius; dx <= radius; dx++) {
+        if (dx * dx + dy * dy > radius * radius) continue;
+        point.set(x + dx, y + dy);
+        if (this.board.set_wall(point, wall)) this.doDraw = true;
+      }
+    }
+    point.free();
+  }
+
+  frame() {
+    return this.board.frame();
+  }
+
+  checksums(start) {
+    return Array.from(this.board.checksums(start));
+  }
+
+  firstDesync(start, theirs) {
+    return this.board.first_desync(start, new Uint32Array(theirs));
+  }
+
+  // `cells` is the width of the board, and `rows` is the height if it isn't square.
+  // A hex grid only wraps top to bottom with an even number of rows.
+  rows() {
+    const rows = this.props.rows ?? this.props.cells;
+    return this.props.torus && this.props.grid == "Hex" ? rows + rows % 2 : rows;
+  }
+
+  pixelsHigh() {
+    return Math.round(this.props.pixels * this.rows() / this.props.cells);
+  }
+
+  // `komi` is White's, as a fraction of the area of the board.
   score(method) {
     const score = this.board.score();
+    const scoring = {
+      stone: wasm.Scoring.Stone,
+      china: wasm.Scoring.China,
+      japan: wasm.Scoring.Japan,
+    }[method];
     const result = {
-      black: score["b_" + method],
-      white: score["w_" + method],
+      black: score.total(scoring, 0),
+      white: score.total(scoring, 1),
+      winner: score.winner(scoring),
+      margin: score.margin(scoring),
+      dame: score.dame,
+      seki: score.seki,
     };
-    score.free();
+    // Kept around for `regionAt`.
+    this.lastScore?.free();
+    this.lastScore = score;
 
     this.doDraw = false;
     if (method != "stone") this.draw(1);
@@ -182,6 +379,23 @@ export default class Board extends Component {
     return result;
   }
 
+  // The region at (x, y) as of the last `score`, or undefined before scoring.
+  regionAt(x, y) {
+    if (this.lastScore == undefined) return undefined;
+    const point = wasm.Point.new(x, y);
+    const region = this.lastScore.region(this.lastScore.region_at(point));
+    point.free();
+    const result = {
+      kind: ["Liquid", "Territory", "Dame", "Seki", "Unclaimed", "Wall"][
+        region.kind()
+      ],
+      player: region.player(),
+      area: region.area(),
+    };
+    region.free();
+    return result;
+  }
+
   render() {
     return (
       <div style={{ display: "flex", alignItems: "stretch" }}>
@@ -220,7 +434,7 @@ export default class Board extends Component {
           <canvas
             ref={this.canvas}
             width={this.props.pixels}
-            height={this.props.pixels}
+            height={this.pixelsHigh()}
           >
           </canvas>
         </div>
@@ -231,47 +445,58 @@ export default class Board extends Component {
   updatePosition = (event) => {
     // `event` is either a MouseEvent or a Touch.
     pauseEvent(event);
+    Object.assign(this.mouse, this.cellAt(event));
+  };
+
+  cellAt = (event) => {
     const rect = this.canvas.current.getBoundingClientRect();
-    this.mouse.x = Math.max(
+    const y = Math.max(
       Math.min(
         Math.round(
-          (event.clientX - rect.left) * this.props.cells / this.props.pixels,
+          (event.clientY - rect.top) * this.props.cells / this.props.pixels,
         ),
-        this.props.cells - 1,
+        this.rows() - 1,
       ),
       0,
     );
-    this.mouse.y = Math.max(
+    const shift = this.props.grid == "Hex" && y % 2 ? 0.5 : 0;
+    const x = Math.max(
       Math.min(
         Math.round(
-          (event.clientY - rect.top) * this.props.cells / this.props.pixels,
+          (event.clientX - rect.left) * this.props.cells / this.props.pixels -
+            shift,
         ),
         this.props.cells - 1,
       ),
       0,
     );
+    return { x, y };
   };
 
   updateTouch = (event, down) => {
-    // We are making the bold assumptions that touch 0 is always first
-    // if present, and when there are no touches a new touch will be touch 0.
     pauseEvent(event);
 
-    let touch;
-    if (this.mouse.left) {
-      for (const t of event.changedTouches) {
-        if (t.identifier == this.mouse.finger) touch = t;
+    const fingers = this.mouse.fingers;
+    for (const touch of event.changedTouches) {
+      let finger = fingers.find((f) => f.active && f.id == touch.identifier);
+      if (!finger && down) {
+        // A new finger takes the first free slot, so the others keep theirs.
+        finger = fingers.find((f) => !f.active);
+        if (!finger) fingers.push(finger = {});
+        finger.id = touch.identifier;
       }
-    } else {
-      touch = event.changedTouches[0];
-      this.mouse.finger = touch.identifier;
+      if (!finger) continue;
+      if (down != null) finger.active = down;
+      Object.assign(finger, this.cellAt(touch));
     }
 
-    if (touch) {
-      if (down != null) {
-        this.mouse.left = down;
-      }
-      this.updatePosition(touch);
+    // The first finger down doubles as the mouse, for everything that only
+    // wants one.
+    const first = fingers.find((f) => f.active) ?? fingers[0];
+    this.mouse.left = fingers.some((f) => f.active);
+    if (first) {
+      this.mouse.x = first.x;
+      this.mouse.y = first.y;
     }
   };
 }
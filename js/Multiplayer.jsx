@@ -14,6 +14,7 @@ export default class Multiplayer extends Component {
       score: undefined,
       prisoners: undefined,
       done: false,
+      desync: undefined,
     };
   }
 
@@ -25,6 +26,8 @@ export default class Multiplayer extends Component {
     rtc.join(this.props.room).then(({ isBlack, dc }) => {
       let theseSamples = [];
       let thoseSamples = [];
+      // Checksums from the other board, waiting for us to reach those frames.
+      let thoseChecksums = [];
       this.dc = dc;
       this.setState({ isBlack });
 
@@ -64,6 +67,28 @@ export default class Multiplayer extends Component {
                 : this.board.current.spill(thatSample, thisSample),
             });
 
+            // Every second, swap checksums for the last second of frames, so
+            // that we can tell exactly where the two boards stopped agreeing.
+            const frame = this.board.current.frame();
+            if (frame % 60 == 0) {
+              dc.send(JSON.stringify({
+                start: frame - 60,
+                checksums: this.board.current.checksums(frame - 60),
+              }));
+            }
+            while (
+              thoseChecksums.length > 0 &&
+              thoseChecksums[0].start + thoseChecksums[0].checksums.length <=
+                frame
+            ) {
+              const { start, checksums } = thoseChecksums.shift();
+              const desync = this.board.current.firstDesync(start, checksums);
+              if (desync != undefined && this.state.desync == undefined) {
+                console.error("Boards diverged at frame", desync);
+                this.setState({ desync });
+              }
+            }
+
             this.timeout++;
             if (thisSample.active || thatSample.active) {
               this.started = true;
@@ -77,6 +102,7 @@ export default class Multiplayer extends Component {
               if (this.timeout == 240 && this.started) {
                 this.setState({ done: true });
                 clearInterval(this.intervalID);
+                this.markDead();
               }
             }
           }
@@ -87,6 +113,10 @@ export default class Multiplayer extends Component {
         const message = JSON.parse(event.data);
         if (message.name != undefined) {
           this.setState({ thatName: message.name });
+        } else if (message.dead != undefined) {
+          this.toggleDead(message.dead);
+        } else if (message.checksums != undefined) {
+          thoseChecksums.push(message);
         } else {
           thoseSamples.push(message);
         }
@@ -94,6 +124,26 @@ export default class Multiplayer extends Component {
     });
   }
 
+  // After the game, either player can click on a body to mark it dead or alive
+  // again, and the score is recounted on both sides.
+  markDead() {
+    let wasDown = false;
+    this.intervalID = setInterval(() => {
+      const mouse = this.board.current.mouse;
+      if (mouse.left && !wasDown) {
+        const dead = { x: mouse.x, y: mouse.y };
+        this.dc.send(JSON.stringify({ dead }));
+        this.toggleDead(dead);
+      }
+      wasDown = mouse.left;
+    }, 17);
+  }
+
+  toggleDead({ x, y }) {
+    this.board.current.toggleDead(x, y);
+    this.setState({ score: this.board.current.score(this.props.scoring) });
+  }
+
   componentWillUnmount() {
     this.dc?.close();
     clearInterval(this.intervalID);
@@ -115,6 +165,8 @@ export default class Multiplayer extends Component {
           }
         })() / (this.props.resolution == "Smooth" ? 1 : 36)}
         cells={this.props.resolution == "Smooth" ? 1200 : 200}
+        komi={this.props.komi}
+        schedule={this.props.schedule}
       >
         {this.state.isBlack == undefined ? <p>Searching for opponent...</p> : (
           <>
@@ -156,15 +208,18 @@ export default class Multiplayer extends Component {
           ? [
             "Game over",
             <br />,
-            (this.state.score.black > this.state.score.white
-              ? "Black"
-              : "White") +
-            " wins by " +
-            formatScore(
-              Math.abs(this.state.score.black - this.state.score.white),
-            ),
+            this.state.score.winner == undefined
+              ? "Draw"
+              : (this.state.score.winner == 0 ? "Black" : "White") +
+                " wins by " + formatScore(this.state.score.margin),
           ]
           : ""}
+        {this.state.done
+          ? [<br />, "Click on groups to mark them dead or alive."]
+          : ""}
+        {this.state.desync != undefined
+          ? [<br />, "Desync detected at frame " + this.state.desync]
+          : ""}
         <div style={{ margin: "auto 0" }} />
         Game info:
         <br />
@@ -179,8 +234,12 @@ export default class Multiplayer extends Component {
           }
         })()}
         <br />
+        Komi: {this.props.komi * 100}% of the board
+        <br />
         Speed: {this.props.flow}
         <br />
+        Turn order: {this.props.schedule}
+        <br />
         Resolution: {this.props.resolution}
         <br />
         {this.props.children}
@@ -1,5 +1,6 @@
 import React, { Component } from "react";
 
+import * as wasm from "../pkg/index";
 import Board from "./Board";
 import { formatScore, mobileOrTablet, Option, Radio } from "./utils";
 
@@ -7,13 +8,26 @@ export default class Singleplayer extends Component {
   constructor(props) {
     super(props);
     this.board = React.createRef();
+    // The Rust bots, made when they're first picked. Keyed by color and kind.
+    this.bots = {};
     this.state = {
       blackStrat: "mouse left",
       whiteStrat: "follow",
       cells: mobileOrTablet() ? 200 : 1200,
+      rows: mobileOrTablet() ? 200 : 1200,
       pixels: 600,
       capturing: true,
+      torus: false,
+      grid: "Square",
+      komi: 0,
+      marking: false,
+      estimate: false,
       flow: 300,
+      ink: 0,
+      refill: 0,
+      sharing: "Split",
+      schedule: "Snake",
+      whiteWeight: 1,
       scoreType: "china",
       score: null,
       prisoners: { black: 0, white: 0 },
@@ -24,6 +38,26 @@ export default class Singleplayer extends Component {
     let then = performance.now();
     let frames = 0;
     this.intervalID = setInterval(() => {
+      const mouse = this.board.current.mouse;
+      if (this.state.marking) {
+        // The game is paused while clicking on bodies to mark them dead.
+        if (mouse.left && !this.wasDown) {
+          this.board.current.toggleDead(mouse.x, mouse.y);
+          this.setState({
+            score: this.board.current.score(this.state.scoreType),
+          });
+        }
+        this.wasDown = mouse.left;
+        return;
+      }
+      if (mouse.middle) {
+        this.board.current.paintWalls(
+          mouse.x,
+          mouse.y,
+          Math.ceil(this.state.cells / 100),
+          true,
+        );
+      }
       this.setState({
         prisoners: this.board.current.spill(
           this.blackSample(),
@@ -43,10 +77,14 @@ export default class Singleplayer extends Component {
 
   componentWillUnmount() {
     clearInterval(this.intervalID);
+    for (const bot of Object.values(this.bots)) bot.free();
+    this.bots = {};
   }
 
   componentDidUpdate(_, prevState) {
-    if (prevState.cells != this.state.cells) {
+    if (
+      prevState.cells != this.state.cells || prevState.rows != this.state.rows
+    ) {
       this.setState({ flow: Math.ceil(this.area() / 4800) });
     }
   }
@@ -59,9 +97,21 @@ export default class Singleplayer extends Component {
       <Board
         ref={this.board}
         cells={this.state.cells <= 0 ? 1 : Number(this.state.cells)}
+        rows={this.state.rows <= 0 ? 1 : Number(this.state.rows)}
         pixels={Number(this.state.pixels)}
         flow={this.state.flow}
         capturing={this.state.capturing}
+        torus={this.state.torus}
+        grid={this.state.grid}
+        komi={this.state.komi <= 0 ? 0 : Number(this.state.komi) / 100}
+        estimate={this.state.estimate}
+        ink={this.state.ink <= 0 ? 0 : Number(this.state.ink)}
+        refill={this.state.refill <= 0 ? 0 : Number(this.state.refill)}
+        sharing={this.state.sharing}
+        schedule={this.state.schedule}
+        whiteWeight={this.state.whiteWeight <= 0
+          ? 0
+          : Number(this.state.whiteWeight)}
       >
         <Radio
           value={this.state.blackStrat}
@@ -72,6 +122,10 @@ export default class Singleplayer extends Component {
           <Option value="mouse right">Right click</Option>
           <Option value="sine">Lattice</Option>
           <Option value="follow">Follow</Option>
+          <Option value="expander">Bot: expander</Option>
+          <Option value="cutter">Bot: cutter</Option>
+          <Option value="defender">Bot: defender</Option>
+          <Option value="rollout">Bot: lookahead (small boards)</Option>
         </Radio>
         <br />
         <Radio
@@ -83,6 +137,10 @@ export default class Singleplayer extends Component {
           <Option value="mouse right">Right click</Option>
           <Option value="sine">Lattice</Option>
           <Option value="follow">Follow</Option>
+          <Option value="expander">Bot: expander</Option>
+          <Option value="cutter">Bot: cutter</Option>
+          <Option value="defender">Bot: defender</Option>
+          <Option value="rollout">Bot: lookahead (small boards)</Option>
         </Radio>
         <br />
         <label>
@@ -107,6 +165,17 @@ export default class Singleplayer extends Component {
           </input>
         </label>
         <br className="big" />
+        <label>
+          Height in cells:
+          <br />
+          <input
+            type="number"
+            value={this.state.rows}
+            onInput={(event) => this.setState({ rows: event.target.value })}
+          >
+          </input>
+        </label>
+        <br className="big" />
         <label>
           <input
             type="checkbox"
@@ -117,6 +186,40 @@ export default class Singleplayer extends Component {
           &nbsp;Enable capturing
         </label>
         <br className="big" />
+        <label>
+          <input
+            type="checkbox"
+            defaultChecked={this.state.torus}
+            onInput={() => this.setState({ torus: !this.state.torus })}
+          >
+          </input>
+          &nbsp;Wrap around edges
+        </label>
+        <br className="big" />
+        <label>
+          Komi for White, in percent of the board:
+          <br />
+          <input
+            type="number"
+            step="0.1"
+            value={this.state.komi}
+            onInput={(event) => this.setState({ komi: event.target.value })}
+          >
+          </input>
+        </label>
+        <br className="big" />
+        <Radio
+          value={this.state.grid}
+          on={(value) => this.setState({ grid: value })}
+        >
+          <legend>Cells:</legend>
+          <Option value="Square">Square</Option>
+          <Option value="Diagonal">Square, connected diagonally</Option>
+          <Option value="Hex">Hexagonal</Option>
+        </Radio>
+        <br />
+        Middle click to paint walls.
+        <br className="big" />
         <label>
           Flow: {this.state.flow} cells per frame
           <br />
@@ -131,6 +234,64 @@ export default class Singleplayer extends Component {
           </input>
         </label>
         <br className="big" />
+        <label>
+          Ink per player, in cells (0 for no limit):
+          <br />
+          <input
+            type="number"
+            value={this.state.ink}
+            onInput={(event) => this.setState({ ink: event.target.value })}
+          >
+          </input>
+        </label>
+        <br className="big" />
+        <label>
+          Ink refilled per frame:
+          <br />
+          <input
+            type="number"
+            value={this.state.refill}
+            onInput={(event) => this.setState({ refill: event.target.value })}
+          >
+          </input>
+        </label>
+        <br className="big" />
+        <Radio
+          value={this.state.schedule}
+          on={(value) => this.setState({ schedule: value })}
+        >
+          <legend>Turn order at the front:</legend>
+          <Option value="Snake">Black, White, White, Black</Option>
+          <Option value="Alternate">Black, White, Black, White</Option>
+          <Option value="Simultaneous">Both at once, ties take turns</Option>
+          <Option value="Weighted">Weighted by flow</Option>
+        </Radio>
+        {this.state.schedule == "Weighted"
+          ? (
+            <label>
+              White's flow compared to Black's:
+              <br />
+              <input
+                type="number"
+                step="0.1"
+                value={this.state.whiteWeight}
+                onInput={(event) =>
+                  this.setState({ whiteWeight: event.target.value })}
+              >
+              </input>
+            </label>
+          )
+          : ""}
+        <br className="big" />
+        <Radio
+          value={this.state.sharing}
+          on={(value) => this.setState({ sharing: value })}
+        >
+          <legend>With more than one finger down:</legend>
+          <Option value="Split">Split the flow between them</Option>
+          <Option value="Each">Every finger gets the full flow</Option>
+        </Radio>
+        <br />
         <Radio
           value={this.state.scoreType}
           on={(value) => this.setState({ scoreType: value })}
@@ -151,10 +312,41 @@ export default class Singleplayer extends Component {
           Calculate score
         </button>
         <br />
+        <label>
+          <input
+            type="checkbox"
+            defaultChecked={this.state.marking}
+            onInput={() => this.setState({ marking: !this.state.marking })}
+          >
+          </input>
+          &nbsp;Pause and click to mark dead groups
+        </label>
+        <br />
+        <label>
+          <input
+            type="checkbox"
+            defaultChecked={this.state.estimate}
+            onInput={() => this.setState({ estimate: !this.state.estimate })}
+          >
+          </input>
+          &nbsp;Show live estimate
+        </label>
+        {this.state.estimate && this.board.current?.projection
+          ? [
+            <br />,
+            "Projected: Black " +
+            formatScore(this.board.current.projection.black) + ", White " +
+            formatScore(this.board.current.projection.white),
+          ]
+          : ""}
+        <br />
         Black:
         <div style={{ marginLeft: "1em" }}>
           {formatScore(this.state.prisoners.black)} prisoners taken
           <br />
+          {this.state.prisoners.blackInk == undefined
+            ? ""
+            : [formatScore(this.state.prisoners.blackInk) + " ink left", <br />]}
           {this.state.score == null
             ? ""
             : formatScore(this.state.score.black) + " points"}
@@ -164,10 +356,27 @@ export default class Singleplayer extends Component {
         <div style={{ marginLeft: "1em" }}>
           {formatScore(this.state.prisoners.white)} prisoners taken
           <br />
+          {this.state.prisoners.whiteInk == undefined
+            ? ""
+            : [formatScore(this.state.prisoners.whiteInk) + " ink left", <br />]}
           {this.state.score == null
             ? ""
             : formatScore(this.state.score.white) + " points"}
         </div>
+        {this.state.score == null
+          ? ""
+          : [
+            <br />,
+            this.state.score.winner == undefined
+              ? "Draw"
+              : (this.state.score.winner == 0 ? "Black" : "White") +
+                " wins by " + formatScore(this.state.score.margin),
+            <br />,
+            formatScore(this.state.score.dame) + " dame, " +
+            formatScore(this.state.score.seki) + " in seki",
+            <br />,
+            this.hoveredRegion(),
+          ]}
         <br />
         <button
           onClick={() => {
@@ -186,11 +395,12 @@ export default class Singleplayer extends Component {
   sampleFunc = (isBlack, strat) => {
     this.board.current?.board.set_speed(isBlack, strat == "follow" ? 0.25 : 1);
     return strat === "mouse left"
-      ? () => ({
-        x: this.board.current.mouse.x,
-        y: this.board.current.mouse.y,
-        active: this.board.current.mouse.left,
-      })
+      ? () =>
+        this.board.current.fingers() ?? {
+          x: this.board.current.mouse.x,
+          y: this.board.current.mouse.y,
+          active: this.board.current.mouse.left,
+        }
       : strat === "mouse right"
       ? () => ({
         x: this.board.current.mouse.x,
@@ -203,7 +413,7 @@ export default class Singleplayer extends Component {
           this.state.cells / 2,
         y: (Math.sin(
           performance.now() / 300 * (1 + Math.sqrt(5)) / 2 + (isBlack ? 20 : 0),
-        ) + 1) * this.state.cells / 2,
+        ) + 1) * this.state.rows / 2,
         active: true,
       })
       : strat === "follow"
@@ -213,8 +423,32 @@ export default class Singleplayer extends Component {
 
         active: true,
       })
+      : ["expander", "cutter", "defender", "rollout"].includes(strat)
+      ? () => this.botSample(isBlack, strat)
       : () => ({ x: 0, y: 0, active: false });
   };
 
-  area = () => this.state.cells * this.state.cells;
+  botSample = (isBlack, kind) => {
+    const key = (isBlack ? "black " : "white ") + kind;
+    this.bots[key] ??= wasm.Bot[kind](this.state.flow);
+    const aim = this.bots[key].aim(this.board.current.board, isBlack ? 0 : 1);
+    const pos = aim.pos;
+    const sample = { x: pos.x, y: pos.y, active: aim.active };
+    pos.free();
+    aim.free();
+    return sample;
+  };
+
+  area = () => this.state.cells * this.state.rows;
+
+  hoveredRegion = () => {
+    const mouse = this.board.current.mouse;
+    const region = this.board.current.regionAt(mouse.x, mouse.y);
+    if (region == undefined) return "";
+    const whose = region.player == undefined
+      ? ""
+      : (region.player == 0 ? "Black " : "White ");
+    return "Under the mouse: " + whose + region.kind + ", " +
+      formatScore(region.area) + " cells";
+  };
 }
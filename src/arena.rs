@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use super::{
+  Board, Cutter, Defender, Expander, Point, Rollout, Schedule, ScoreResult, Strategy, Team, View,
+};
+
+/// The names `strategy` knows, for command lines and tournaments.
+pub const STRATEGIES: &[&str] = &["expander", "cutter", "defender", "rollout"];
+
+/// A fresh bot by name. `flow` and `seed` are for the bots that look ahead, the
+/// others ignore them.
+pub fn strategy(name: &str, flow: u32, seed: u64) -> Option<Box<dyn Strategy>> {
+  Some(match name {
+    "expander" => Box::<Expander>::default(),
+    "cutter" => Box::<Cutter>::default(),
+    "defender" => Box::<Defender>::default(),
+    "rollout" => {
+      let mut rollout = Rollout::with_seed(seed);
+      rollout.flow = flow;
+      Box::new(rollout)
+    }
+    _ => return None,
+  })
+}
+
+/// The rules for a bot game, Black against White on an empty board.
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+  pub width: usize,
+  pub height: usize,
+  /// The `count` passed to `spill` every frame.
+  pub flow: u32,
+  pub capturing: bool,
+  /// Both players' spigot speed, see `set_player_speed`.
+  pub speed: f32,
+  /// White's komi, as a fraction of the board like `set_komi_fraction`.
+  pub komi: f32,
+  pub schedule: Schedule,
+  /// The game is also over once the cells have only been going back to how they
+  /// were before for this many frames, eg. because both bots passed.
+  pub quiet: usize,
+  /// Give up after this many frames, in case the bots never settle down.
+  pub max_frames: usize,
+}
+
+impl Default for MatchConfig {
+  fn default() -> Self {
+    MatchConfig {
+      width: 64,
+      height: 64,
+      flow: 4,
+      capturing: true,
+      speed: 1.,
+      komi: 0.,
+      schedule: Schedule::Snake,
+      quiet: 60,
+      max_frames: 20_000,
+    }
+  }
+}
+
+/// How a bot game went.
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+  pub score: ScoreResult,
+  pub frames: usize,
+  /// False if the game hit `max_frames` instead of ending by itself.
+  pub finished: bool,
+}
+
+/// Play `black` against `white` headlessly, flipping who goes first every frame
+/// like the browser does.
+pub fn play_match(
+  config: &MatchConfig,
+  black: &mut dyn Strategy,
+  white: &mut dyn Strategy,
+) -> MatchResult {
+  let mut board = Board::with_players(config.width, config.height, config.capturing, 2);
+  board.set_schedule(config.schedule);
+  for player in 0..2 {
+    board.set_player_speed(player, config.speed);
+  }
+  board.set_komi_fraction(1, config.komi);
+
+  // Layouts of the cells we've seen, to notice when the game's going round in
+  // circles, like one bot pouring into the other's eye and getting captured.
+  let mut seen = HashSet::from([board.teams_hash]);
+  let mut repeats = 0;
+  while repeats < config.quiet && board.frame() < config.max_frames {
+    let view = View::new(&board);
+    let spigots = [black.aim(&view, 0).spigot(), white.aim(&view, 1).spigot()];
+    board.spill_players(&spigots, config.flow, board.frame() % 2);
+    repeats = if seen.insert(board.teams_hash) { 0 } else { repeats + 1 };
+    if settled(&board) {
+      break;
+    }
+  }
+  let finished = board.frame() < config.max_frames;
+  MatchResult { score: board.score(), frames: board.frame(), finished }
+}
+
+/// Whether every empty cell is somebody's territory, which ends the game like
+/// both players passing when there's no dame left in Go. Bots don't know when to
+/// stop, so we have to tell them: left alone they fill in their own eyes until
+/// the whole board is liquid, and then whoever spills last captures everything.
+/// That's `score` finding no dame, seki or unclaimed board, but cheap enough to
+/// check every frame.
+fn settled(board: &Board) -> bool {
+  let mut seen = vec![false; board.area()];
+  for start in 0..board.area() {
+    if board.teams[start] != Team::EMPTY || seen[start] {
+      continue;
+    }
+    seen[start] = true;
+    let mut stack = vec![start];
+    let mut touching = None;
+    while let Some(idx) = stack.pop() {
+      let pos = Point::new((idx % board.width) as isize, (idx / board.width) as isize);
+      for neighbor in board.neighbors(pos) {
+        let n_idx = board.get_idx(neighbor);
+        match board.teams[n_idx].player() {
+          Some(player) if touching.is_some_and(|p| p != player) => return false,
+          Some(player) => touching = Some(player),
+          None if board.teams[n_idx] == Team::EMPTY && !seen[n_idx] => {
+            seen[n_idx] = true;
+            stack.push(n_idx);
+          }
+          None => (),
+        }
+      }
+    }
+    if touching.is_none() {
+      return false;
+    }
+  }
+  (0..board.players()).all(|player| board.teams.contains(&Team(player as u8)))
+}
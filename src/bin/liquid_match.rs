@@ -0,0 +1,230 @@
+//! Bot against bot, without a browser. Prints each game's `ScoreResult` as a
+//! line of JSON, so thousands of games can be piped into whatever's tuning the
+//! bots or the rules. With `--tournament` it plays a round robin instead and
+//! prints ratings. Run `liquid_match --help` for the options.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use liquid_go::{
+  play_match, strategy, MatchConfig, MatchResult, Schedule, Scoring, Tournament, STRATEGIES,
+};
+use serde::Serialize;
+
+const USAGE: &str = "\
+usage: liquid_match [options] <black> <white>
+       liquid_match --tournament [options] [bots...]
+
+Bots: expander, cutter, defender, rollout. A tournament plays every pair of
+bots (all of them if none are given) --games times each way round, and prints
+their Elo ratings, a table of results and how often Black won.
+
+options:
+  --size N          a square board (default 64)
+  --width N         board width
+  --height N        board height
+  --flow N          cells spilled per frame (default 4)
+  --no-capturing    turn captures off
+  --speed X         how fast spigots follow where the bots aim, more than 0
+                    and at most 2 (default 1)
+  --komi X          White's komi as a fraction of the board (default 0)
+  --schedule S      snake, alternate, simultaneous or weighted (default snake)
+  --quiet N         frames of repeated positions that end the game (default 60)
+  --max-frames N    give up after this many frames (default 20000)
+  --games N         how many games to play, or per pair and color in a
+                    tournament (default 1)
+  --scoring S       stone, china or japan, for the tournament (default china)
+  --threads N       games to play at once (default: one per core)
+  --seed N          seed for the bots that use randomness, +1 every game (default 0)
+";
+
+fn fail(message: &str) -> ! {
+  eprintln!("{}\n\n{}", message, USAGE);
+  std::process::exit(2);
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+  let value = value.unwrap_or_else(|| fail(&format!("{} needs a value", flag)));
+  value.parse().unwrap_or_else(|_| fail(&format!("bad value for {}: {}", flag, value)))
+}
+
+/// `parse` for settings that end up in the scores, which have to be numbers.
+fn finite(flag: &str, value: Option<String>) -> f32 {
+  let x: f32 = parse(flag, value);
+  if !x.is_finite() {
+    fail(&format!("{} has to be a number", flag));
+  }
+  x
+}
+
+/// `finite` for `--speed`, which the spigots can only follow up to 2.
+fn speed(flag: &str, value: Option<String>) -> f32 {
+  let x = finite(flag, value);
+  if !(x > 0. && x <= 2.) {
+    fail(&format!("{} has to be more than 0 and at most 2", flag));
+  }
+  x
+}
+
+/// One line of output: who played, how long it took and the numbers from the
+/// `ScoreResult`, in player order.
+#[derive(Serialize)]
+struct Game<'a> {
+  game: usize,
+  black: &'a str,
+  white: &'a str,
+  frames: usize,
+  finished: bool,
+  stone: [isize; 2],
+  china: [isize; 2],
+  japan: [isize; 2],
+  komi: [f32; 2],
+  dame: isize,
+  seki: isize,
+  winner: ByScoring<Option<usize>>,
+  margin: ByScoring<f32>,
+}
+
+#[derive(Serialize)]
+struct ByScoring<T> {
+  stone: T,
+  china: T,
+  japan: T,
+}
+
+impl<T> ByScoring<T> {
+  fn new(f: impl Fn(Scoring) -> T) -> Self {
+    ByScoring { stone: f(Scoring::Stone), china: f(Scoring::China), japan: f(Scoring::Japan) }
+  }
+}
+
+impl<'a> Game<'a> {
+  fn new(game: usize, black: &'a str, white: &'a str, result: &MatchResult) -> Self {
+    let score = &result.score;
+    Game {
+      game,
+      black,
+      white,
+      frames: result.frames,
+      finished: result.finished,
+      stone: [0, 1].map(|p| score.stone(p)),
+      china: [0, 1].map(|p| score.china(p)),
+      japan: [0, 1].map(|p| score.japan(p)),
+      komi: [0, 1].map(|p| score.komi(p)),
+      dame: score.dame,
+      seki: score.seki,
+      winner: ByScoring::new(|scoring| score.winner(scoring)),
+      margin: ByScoring::new(|scoring| score.margin(scoring)),
+    }
+  }
+}
+
+fn schedule(name: &str) -> Schedule {
+  match name {
+    "snake" => Schedule::Snake,
+    "alternate" => Schedule::Alternate,
+    "simultaneous" => Schedule::Simultaneous,
+    "weighted" => Schedule::Weighted,
+    _ => fail(&format!("unknown schedule: {}", name)),
+  }
+}
+
+fn scoring(name: &str) -> Scoring {
+  match name {
+    "stone" => Scoring::Stone,
+    "china" => Scoring::China,
+    "japan" => Scoring::Japan,
+    _ => fail(&format!("unknown scoring: {}", name)),
+  }
+}
+
+fn main() {
+  let mut config = MatchConfig::default();
+  let mut games = 1;
+  let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+  let mut seed: u64 = 0;
+  let mut tournament = false;
+  let mut counting = Scoring::China;
+  let mut bots = vec![];
+
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-h" | "--help" => {
+        print!("{}", USAGE);
+        return;
+      }
+      "--size" => {
+        config.width = parse(&arg, args.next());
+        config.height = config.width;
+      }
+      "--width" => config.width = parse(&arg, args.next()),
+      "--height" => config.height = parse(&arg, args.next()),
+      "--flow" => config.flow = parse(&arg, args.next()),
+      "--no-capturing" => config.capturing = false,
+      "--speed" => config.speed = speed(&arg, args.next()),
+      "--komi" => config.komi = finite(&arg, args.next()),
+      "--schedule" => config.schedule = schedule(&parse::<String>(&arg, args.next())),
+      "--quiet" => config.quiet = parse(&arg, args.next()),
+      "--max-frames" => config.max_frames = parse(&arg, args.next()),
+      "--games" => games = parse(&arg, args.next()),
+      "--threads" => threads = parse(&arg, args.next()),
+      "--seed" => seed = parse(&arg, args.next()),
+      "--tournament" => tournament = true,
+      "--scoring" => counting = scoring(&parse::<String>(&arg, args.next())),
+      _ if arg.starts_with('-') => fail(&format!("unknown option: {}", arg)),
+      _ if STRATEGIES.contains(&arg.as_str()) => bots.push(arg),
+      _ => fail(&format!("unknown bot: {}", arg)),
+    }
+  }
+  if tournament {
+    if bots.is_empty() {
+      bots = STRATEGIES.iter().map(|name| name.to_string()).collect();
+    }
+    if bots.len() < 2 {
+      fail("a tournament needs at least two bots");
+    }
+  } else if bots.len() != 2 {
+    fail("pick two bots");
+  }
+
+  // Every game to play, as (black, white) indices into bots.
+  let mut pairings = vec![(0, 1); games];
+  if tournament {
+    pairings.clear();
+    for i in 0..bots.len() {
+      for j in i + 1..bots.len() {
+        pairings.extend(vec![(i, j); games]);
+        pairings.extend(vec![(j, i); games]);
+      }
+    }
+  }
+  let names: Vec<&str> = bots.iter().map(|name| name.as_str()).collect();
+  let results = Mutex::new(Tournament::new(&names, counting));
+
+  // Each thread takes the next game until they've all been played. Lines come out
+  // in the order games finish, so each one says which game it was.
+  let next = AtomicUsize::new(0);
+  thread::scope(|scope| {
+    for _ in 0..threads.clamp(1, pairings.len().max(1)) {
+      scope.spawn(|| loop {
+        let game = next.fetch_add(1, Ordering::Relaxed);
+        let Some(&(black, white)) = pairings.get(game) else { break };
+        let seed = 2 * (seed + game as u64);
+        let mut b = strategy(names[black], config.flow, seed).unwrap();
+        let mut w = strategy(names[white], config.flow, seed + 1).unwrap();
+        let result = play_match(&config, b.as_mut(), w.as_mut());
+        if tournament {
+          results.lock().unwrap().record(black, white, &result);
+        } else {
+          let line = Game::new(game, names[black], names[white], &result);
+          println!("{}", serde_json::to_string(&line).expect("a Game is always serializable"));
+        }
+      });
+    }
+  });
+  if tournament {
+    print!("{}", results.into_inner().unwrap().table());
+  }
+}
@@ -0,0 +1,323 @@
+use std::cell::OnceCell;
+use std::collections::VecDeque;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Point, Team};
+
+/// A computer player. Every frame it looks at the board and says where its spigot
+/// goes, the same as a player moving the mouse.
+pub trait Strategy {
+  /// Called once a frame, before `spill`, for `player`.
+  fn aim(&mut self, view: &View, player: usize) -> Aim;
+}
+
+/// Where a `Strategy` wants its spigot, and whether it's on.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aim {
+  pub pos: Point,
+  pub active: bool,
+}
+
+impl Aim {
+  pub fn at(pos: Point) -> Self {
+    Aim { pos, active: true }
+  }
+
+  /// Passing.
+  pub fn off() -> Self {
+    Aim { pos: Point::new(0, 0), active: false }
+  }
+
+  /// The spigot for `spill_players`.
+  pub fn spigot(self) -> Option<Point> {
+    if self.active {
+      Some(self.pos)
+    } else {
+      None
+    }
+  }
+}
+
+/// A body of liquid, as a `Strategy` sees it.
+#[derive(Clone, Debug)]
+pub struct BodyInfo {
+  pub player: usize,
+  pub cells: Vec<Point>,
+  /// The empty cells touching it, each once.
+  pub liberties: Vec<Point>,
+}
+
+/// What a `Strategy` gets to see: the board, read only. The bodies are worked out
+/// from the cells the first time they're asked for, so bots that don't look at
+/// them don't pay for them.
+pub struct View<'a> {
+  board: &'a Board,
+  bodies: OnceCell<(Vec<BodyInfo>, Vec<usize>)>,
+}
+
+/// body_at for cells that aren't liquid.
+const NO_BODY: usize = usize::MAX;
+
+impl<'a> View<'a> {
+  pub fn new(board: &'a Board) -> Self {
+    View { board, bodies: OnceCell::new() }
+  }
+
+  pub fn board(&self) -> &Board {
+    self.board
+  }
+
+  pub fn width(&self) -> usize {
+    self.board.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.board.height
+  }
+
+  /// The number of frames so far.
+  pub fn frame(&self) -> usize {
+    self.board.frame()
+  }
+
+  pub fn contains(&self, pos: &Point) -> bool {
+    (0..self.width() as isize).contains(&pos.x) && (0..self.height() as isize).contains(&pos.y)
+  }
+
+  /// Whose liquid is on `pos`, if anyone's.
+  pub fn player_at(&self, pos: &Point) -> Option<usize> {
+    self.board.get_teams(*pos).player()
+  }
+
+  pub fn is_empty(&self, pos: &Point) -> bool {
+    self.board.get_teams(*pos) == Team::EMPTY
+  }
+
+  pub fn is_wall(&self, pos: &Point) -> bool {
+    self.board.get_teams(*pos) == Team::WALL
+  }
+
+  /// The cells next to `pos`, going by the board's grid and topology.
+  pub fn neighbors(&self, pos: &Point) -> impl Iterator<Item = Point> {
+    self.board.neighbors(*pos)
+  }
+
+  /// Where `player`'s first spigot is this frame, or None if it's off.
+  pub fn spigot(&self, player: usize) -> Option<Point> {
+    self.board.players[player].spigots[0].pos.map(Point::from_fixed)
+  }
+
+  /// Every body of liquid on the board.
+  pub fn bodies(&self) -> &[BodyInfo] {
+    &self.flood().0
+  }
+
+  /// The body `pos` is part of, if it's liquid.
+  pub fn body_at(&self, pos: &Point) -> Option<&BodyInfo> {
+    let (bodies, body_at) = self.flood();
+    bodies.get(body_at[self.board.get_idx(*pos)])
+  }
+
+  /// The empty cell farthest from any liquid, counting steps around walls, or the
+  /// middle of the board if there's no liquid yet. None if nothing is empty.
+  pub fn farthest_empty(&self) -> Option<Point> {
+    let board = self.board;
+    let mut steps = vec![u32::MAX; board.area()];
+    let mut queue: VecDeque<usize> = (0..board.area())
+      .filter(|&idx| board.teams[idx].player().is_some())
+      .inspect(|&idx| steps[idx] = 0)
+      .collect();
+    if queue.is_empty() {
+      let middle = Point::new(self.width() as isize / 2, self.height() as isize / 2);
+      if self.contains(&middle) && self.is_empty(&middle) {
+        return Some(middle);
+      }
+    }
+    let mut farthest = None;
+    while let Some(idx) = queue.pop_front() {
+      for neighbor in board.neighbors(self.point(idx)) {
+        let n_idx = board.get_idx(neighbor);
+        if board.teams[n_idx] == Team::EMPTY && steps[n_idx] == u32::MAX {
+          steps[n_idx] = steps[idx] + 1;
+          farthest = Some(neighbor);
+          queue.push_back(n_idx);
+        }
+      }
+    }
+    // Empty space that no liquid can reach is as far as it gets.
+    (0..board.area())
+      .find(|&idx| board.teams[idx] == Team::EMPTY && steps[idx] == u32::MAX)
+      .map(|idx| self.point(idx))
+      .or(farthest)
+  }
+
+  fn point(&self, idx: usize) -> Point {
+    Point::new((idx % self.width()) as isize, (idx / self.width()) as isize)
+  }
+
+  fn flood(&self) -> &(Vec<BodyInfo>, Vec<usize>) {
+    self.bodies.get_or_init(|| {
+      let board = self.board;
+      let mut bodies: Vec<BodyInfo> = vec![];
+      let mut body_at = vec![NO_BODY; board.area()];
+      // Which body last counted each empty cell as a liberty, to count it once.
+      let mut counted = vec![NO_BODY; board.area()];
+      for start in 0..board.area() {
+        let Some(player) = board.teams[start].player() else { continue };
+        if body_at[start] != NO_BODY {
+          continue;
+        }
+        let key = bodies.len();
+        let mut body = BodyInfo { player, cells: vec![], liberties: vec![] };
+        body_at[start] = key;
+        let mut stack = vec![start];
+        while let Some(idx) = stack.pop() {
+          let pos = self.point(idx);
+          body.cells.push(pos);
+          for neighbor in board.neighbors(pos) {
+            let n_idx = board.get_idx(neighbor);
+            if board.teams[n_idx] == board.teams[start] && body_at[n_idx] == NO_BODY {
+              body_at[n_idx] = key;
+              stack.push(n_idx);
+            } else if board.teams[n_idx] == Team::EMPTY && counted[n_idx] != key {
+              counted[n_idx] = key;
+              body.liberties.push(neighbor);
+            }
+          }
+        }
+        bodies.push(body);
+      }
+      (bodies, body_at)
+    })
+  }
+}
+
+/// The square of the distance between two cells, ignoring the topology.
+fn distance(a: &Point, b: &Point) -> isize {
+  (a.x - b.x).pow(2) + (a.y - b.y).pow(2)
+}
+
+/// Claims open space: it pours into whatever empty cell is farthest from any
+/// liquid, and picks a new spot once that one's taken.
+#[derive(Clone, Debug, Default)]
+pub struct Expander {
+  target: Option<Point>,
+  since: usize,
+}
+
+/// How many frames bots stick with a target before looking around again.
+const PATIENCE: usize = 60;
+
+impl Strategy for Expander {
+  fn aim(&mut self, view: &View, _player: usize) -> Aim {
+    let stale = match self.target {
+      Some(target) => !view.is_empty(&target) || view.frame() >= self.since + PATIENCE,
+      None => true,
+    };
+    if stale {
+      self.target = view.farthest_empty();
+      self.since = view.frame();
+    }
+    self.target.map_or(Aim::off(), Aim::at)
+  }
+}
+
+/// Goes for the enemy body with the fewest liberties and fills them in, hoping to
+/// capture it. With no enemies on the board it plays like an `Expander`.
+#[derive(Clone, Debug, Default)]
+pub struct Cutter {
+  last: Option<Point>,
+  fallback: Expander,
+}
+
+impl Strategy for Cutter {
+  fn aim(&mut self, view: &View, player: usize) -> Aim {
+    let weakest = view
+      .bodies()
+      .iter()
+      .filter(|body| body.player != player && !body.liberties.is_empty())
+      .min_by_key(|body| (body.liberties.len(), std::cmp::Reverse(body.cells.len())));
+    let Some(weakest) = weakest else {
+      return self.fallback.aim(view, player);
+    };
+    // Sticking close to where we were keeps the spigot from jumping around.
+    let last = self.last.unwrap_or(weakest.cells[0]);
+    let target = *weakest.liberties.iter().min_by_key(|lib| distance(lib, &last)).unwrap();
+    self.last = Some(target);
+    Aim::at(target)
+  }
+}
+
+/// Looks after its own bodies: when one is running short of liberties it pours
+/// into it to push out into open space. Otherwise it plays like an `Expander`.
+#[derive(Clone, Debug)]
+pub struct Defender {
+  /// A body with fewer liberties than this is in danger.
+  pub threshold: usize,
+  fallback: Expander,
+}
+
+impl Default for Defender {
+  fn default() -> Self {
+    Defender { threshold: 24, fallback: Expander::default() }
+  }
+}
+
+impl Strategy for Defender {
+  fn aim(&mut self, view: &View, player: usize) -> Aim {
+    let weakest = view
+      .bodies()
+      .iter()
+      .filter(|body| body.player == player && !body.liberties.is_empty())
+      .min_by_key(|body| body.liberties.len())
+      .filter(|body| body.liberties.len() < self.threshold);
+    let Some(weakest) = weakest else {
+      return self.fallback.aim(view, player);
+    };
+    // Grow towards the liberty with the most room around it.
+    let room = |lib: &&Point| view.neighbors(lib).filter(|n| view.is_empty(n)).count();
+    Aim::at(*weakest.liberties.iter().max_by_key(room).unwrap())
+  }
+}
+
+/// A built in `Strategy`, for playing against from js.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Bot {
+  strategy: Box<dyn Strategy>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Bot {
+  pub fn expander() -> Bot {
+    Bot::new(Box::<Expander>::default())
+  }
+
+  pub fn cutter() -> Bot {
+    Bot::new(Box::<Cutter>::default())
+  }
+
+  pub fn defender() -> Bot {
+    Bot::new(Box::<Defender>::default())
+  }
+
+  /// A `Rollout` bot. It's much slower than the others, so give it a small board.
+  pub fn rollout(flow: u32) -> Bot {
+    let mut rollout = super::Rollout::default();
+    rollout.flow = flow;
+    Bot::new(Box::new(rollout))
+  }
+
+  /// Where the bot playing as `player` wants to spill this frame.
+  pub fn aim(&mut self, board: &Board, player: usize) -> Aim {
+    self.strategy.aim(&View::new(board), player)
+  }
+}
+
+impl Bot {
+  pub fn new(strategy: Box<dyn Strategy>) -> Self {
+    Bot { strategy }
+  }
+}
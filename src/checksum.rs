@@ -0,0 +1,82 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Fixed, Team};
+
+/// splitmix64's finalizer. Good enough to make Zobrist keys on the fly, so we
+/// don't need a table of random numbers the size of the board.
+#[inline]
+fn mix(mut x: u64) -> u64 {
+  x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+  x ^ (x >> 31)
+}
+
+/// The Zobrist key for cell `idx` holding `team`. Empty cells are 0, so that an
+/// empty board hashes to 0 and we only need to do work where there's liquid.
+#[inline]
+pub(crate) fn cell_key(idx: usize, team: Team) -> u64 {
+  match team {
+    Team::EMPTY => 0,
+    Team(player) => mix((idx as u64) << 8 | player as u64),
+  }
+}
+
+/// Hash `teams` from scratch. `Board` keeps this up to date incrementally in
+/// `teams_hash`, this is for when a board is built some other way.
+pub(crate) fn hash_teams(teams: &[Team]) -> u64 {
+  teams.iter().enumerate().fold(0, |hash, (idx, &team)| hash ^ cell_key(idx, team))
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// A hash of everything that affects future frames: the cells, the prisoner
+  /// counts, the spigot dynamics and the settings. Two boards in lockstep should
+  /// always agree on this. It's cheap, since the cells are hashed incrementally.
+  pub fn checksum(&self) -> u32 {
+    let spigot = |pos: Option<[Fixed; 2]>| match pos {
+      Some([x, y]) => x.to_bits().rotate_left(32) ^ y.to_bits(),
+      None => u64::MAX,
+    };
+    let hash = self
+      .players
+      .iter()
+      .flat_map(|player| {
+        [
+          player.prisoners as u64,
+          player.lost as u64,
+          player.speed.to_bits(),
+          player.weight.to_bits(),
+          player.ink.map_or(u64::MAX, |ink| ink.left as u64),
+        ]
+        .into_iter()
+        .chain(player.spigots.iter().flat_map(|s| [spigot(s.pos), spigot(s.tail)]))
+      })
+      .chain([
+        self.capturing as u64,
+        self.topology as u64,
+        self.grid as u64,
+        self.sharing as u64,
+        self.schedule as u64,
+      ])
+      .fold(self.teams_hash, |hash, word| mix(hash ^ word));
+    (hash ^ hash >> 32) as u32
+  }
+
+  /// The number of frames (calls to `spill`) so far.
+  pub fn frame(&self) -> usize {
+    self.checksums.len()
+  }
+
+  /// The checksum after each frame from `start` on, for sending to a peer.
+  pub fn checksums(&self, start: usize) -> Vec<u32> {
+    self.checksums.get(start..).unwrap_or_default().to_vec()
+  }
+
+  /// Compare a peer's `checksums(start)` against ours, and return the first
+  /// frame where the games differ. Frames we haven't played yet are ignored.
+  pub fn first_desync(&self, start: usize, theirs: &[u32]) -> Option<usize> {
+    let ours = self.checksums.get(start..).unwrap_or_default();
+    ours.iter().zip(theirs).position(|(ours, theirs)| ours != theirs).map(|offset| start + offset)
+  }
+}
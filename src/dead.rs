@@ -0,0 +1,55 @@
+use union_find::UnionFind;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Input, Point};
+
+/// The end of the game, where the players agree on which bodies couldn't have
+/// lived. Marking doesn't change the board, only how `score` counts it: dead
+/// liquid is lost by its player and counts as territory for whoever surrounds it.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Mark the body at `pos` dead, or alive again if it already was. Returns
+  /// whether it's marked dead now, which is false if there's no liquid at `pos`.
+  pub fn toggle_dead(&mut self, pos: &Point) -> bool {
+    let pos = *pos;
+    if !(0..self.width as isize).contains(&pos.x)
+      || !(0..self.height as isize).contains(&pos.y)
+      || self.get_teams(pos).player().is_none()
+    {
+      return false;
+    }
+    self.replay.push(Input::ToggleDead(pos));
+    let body = self.bodies.get_mut(self.get_owner(pos));
+    body.marked = !body.marked;
+    body.marked
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Scoring;
+
+  #[test]
+  fn dead_liquid_counts_for_whoever_surrounds_it() {
+    // Black and White walls with territory behind them, and a Black cell that's
+    // strayed into White's.
+    let mut board = Board::new(10, true);
+    board.place_rect(1, &Point::new(3, 0), 1, 10);
+    board.place_rect(0, &Point::new(6, 0), 1, 10);
+    board.place(0, &[Point::new(1, 5)]);
+    let score = board.score();
+    assert_eq!((score.dame, score.china(0), score.china(1)), (49, 41, 10));
+
+    assert!(board.toggle_dead(&Point::new(1, 5)));
+    let score = board.score();
+    assert_eq!((score.dame, score.china(0), score.china(1)), (20, 40, 40));
+    assert_eq!((score.japan(0), score.japan(1)), (29, 30));
+    assert_eq!(score.winner(Scoring::Japan), Some(1));
+
+    assert!(!board.toggle_dead(&Point::new(1, 5)));
+    assert_eq!(board.score().dame, 49);
+    assert!(!board.toggle_dead(&Point::new(0, 0)));
+  }
+}
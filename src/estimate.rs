@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use union_find::UnionFind;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Point, Team};
+
+/// A guess at how a game in progress will end, from `Board::estimate`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Estimate {
+  area: Vec<isize>,
+  komi: Vec<f32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Estimate {
+  /// `player`'s liquid plus the empty board they're projected to get, with komi.
+  /// This is Chinese scoring, so it's comparable to `ScoreResult::total`.
+  pub fn projected(&self, player: usize) -> f32 {
+    self.area[player] as f32 + self.komi[player]
+  }
+
+  /// The player projected to win, or None if it's too close to call.
+  pub fn leader(&self) -> Option<usize> {
+    let mut ranking: Vec<usize> = (0..self.area.len()).collect();
+    ranking.sort_by(|&a, &b| self.projected(b).total_cmp(&self.projected(a)));
+    match ranking[..] {
+      [first, second, ..] if self.projected(first) == self.projected(second) => None,
+      [first, ..] => Some(first),
+      [] => None,
+    }
+  }
+}
+
+/// No player has reached this cell yet.
+const NOBODY: u8 = u8::MAX;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Guess who'll end up with the empty board, without touching `alt_image`. Each
+  /// player's liquid spreads out through the empty board, and a cell is theirs if
+  /// they get there within `reach` steps and at least `margin` steps before anyone
+  /// else, so the contested ground between players is worn away. Walls block the
+  /// spreading, and liquid marked dead counts as empty. It's a breadth first search
+  /// over the board, about half the cost of `score`, so it can run every few
+  /// frames on a small board or every second or so on a big one.
+  ///
+  /// This also draws the ownership heatmap, `get_image_slice(2)`: each player's
+  /// color over the empty board they're projected to get, more opaque the surer
+  /// it is, and transparent everywhere else, for blending over the board.
+  pub fn estimate(&mut self, reach: u32, margin: u32) -> Estimate {
+    let area = self.area();
+    let mut teams = self.teams.clone();
+    for (team, &owner) in teams.iter_mut().zip(&self.owner) {
+      if team.player().is_some() && self.bodies.get(owner).marked {
+        *team = Team::EMPTY;
+      }
+    }
+
+    // The nearest two players to each cell, and how many steps away they are.
+    // Spreading breadth first means the first to arrive is the nearest.
+    let mut first = vec![(NOBODY, u32::MAX); area];
+    let mut second = vec![(NOBODY, u32::MAX); area];
+    let mut queue = VecDeque::new();
+    for (idx, team) in teams.iter().enumerate() {
+      if let Some(player) = team.player() {
+        first[idx] = (player as u8, 0);
+        queue.push_back((idx, player as u8, 0));
+      }
+    }
+    while let Some((idx, player, steps)) = queue.pop_front() {
+      if steps >= reach {
+        continue;
+      }
+      let pos = Point::new((idx % self.width) as isize, (idx / self.width) as isize);
+      for neighbor in self.neighbors(pos) {
+        let neighbor = self.get_idx(neighbor);
+        if teams[neighbor] != Team::EMPTY {
+          continue;
+        }
+        if first[neighbor].0 == NOBODY {
+          first[neighbor] = (player, steps + 1);
+        } else if first[neighbor].0 != player && second[neighbor].0 == NOBODY {
+          second[neighbor] = (player, steps + 1);
+        } else {
+          continue;
+        }
+        queue.push_back((neighbor, player, steps + 1));
+      }
+    }
+
+    let mut projected = vec![0; self.players.len()];
+    self.heatmap.resize(area, 0);
+    for idx in 0..area {
+      let ((player, near), (_, far)) = (first[idx], second[idx]);
+      self.heatmap[idx] = 0;
+      if player == NOBODY || far - near < margin {
+        continue;
+      }
+      projected[player as usize] += 1;
+      if teams[idx] == Team::EMPTY {
+        let sure = ((far - near) as f32 / (2 * margin.max(1)) as f32).min(1.);
+        let alpha = (0x40 as f32 + 0x80 as f32 * sure) as u32;
+        self.heatmap[idx] = self.players[player as usize].color & 0x_00_ff_ff_ff | alpha << 24;
+      }
+    }
+
+    Estimate { area: projected, komi: self.players.iter().map(|player| player.komi).collect() }
+  }
+}
@@ -0,0 +1,66 @@
+use std::ops::{Add, Mul, Sub};
+
+use serde::{Deserialize, Serialize};
+
+/// Bits after the binary point. Positions only need to be a lot finer than a
+/// cell, and this leaves plenty of room above for boards and products.
+const FRAC_BITS: u32 = 20;
+
+/// A fixed point number, for the spigot dynamics. Floats would probably give the
+/// same answers everywhere too, but lockstep games and replays fall apart if
+/// they don't, and integer math can't differ between browsers and builds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct Fixed(i64);
+
+impl Fixed {
+  pub(crate) fn from_int(n: isize) -> Self {
+    Fixed((n as i64) << FRAC_BITS)
+  }
+
+  /// Only for settings coming in from outside. Scaling by a power of two and
+  /// rounding are exact in IEEE floats, so this is the same everywhere.
+  pub(crate) fn from_f32(x: f32) -> Self {
+    Fixed((x * (1 << FRAC_BITS) as f32).round() as i64)
+  }
+
+  /// Rounds towards negative infinity.
+  pub(crate) fn floor(self) -> isize {
+    (self.0 >> FRAC_BITS) as isize
+  }
+
+  /// Rounds towards zero.
+  pub(crate) fn div_int(self, n: u32) -> Self {
+    Fixed(self.0 / n as i64)
+  }
+
+  pub(crate) fn to_bits(self) -> u64 {
+    self.0 as u64
+  }
+}
+
+impl Add for Fixed {
+  type Output = Fixed;
+
+  fn add(self, rhs: Fixed) -> Fixed {
+    Fixed(self.0 + rhs.0)
+  }
+}
+
+impl Sub for Fixed {
+  type Output = Fixed;
+
+  fn sub(self, rhs: Fixed) -> Fixed {
+    Fixed(self.0 - rhs.0)
+  }
+}
+
+impl Mul for Fixed {
+  type Output = Fixed;
+
+  /// Rounds towards negative infinity, like `floor`. Saturates rather than
+  /// overflowing, so a bad setting can't take the whole game down.
+  fn mul(self, rhs: Fixed) -> Fixed {
+    let product = (self.0 as i128 * rhs.0 as i128) >> FRAC_BITS;
+    Fixed(product.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+  }
+}
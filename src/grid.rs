@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Topology};
+
+/// The shape of the cells.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Grid {
+  /// Squares, touching the 4 cells above, below, left and right.
+  Square,
+  /// Squares that also touch the 4 cells at their corners, so liquid connects and
+  /// finds liberties diagonally. Two diagonal lines of liquid can cross each other.
+  Diagonal,
+  /// Hexagons, touching 6 cells. The image is still `width` by `height` cells in
+  /// rows, but every odd row should be drawn half a cell to the right of the even
+  /// rows. On a `Topology::Torus` the top and bottom only line up if the height
+  /// is even, so `set_grid` and `set_topology` refuse to make that board.
+  Hex,
+}
+
+const SQUARE: [(isize, isize); 4] = [(-1, 0), (0, -1), (1, 0), (0, 1)];
+const DIAGONAL: [(isize, isize); 8] =
+  [(-1, 0), (0, -1), (1, 0), (0, 1), (-1, -1), (1, -1), (1, 1), (-1, 1)];
+const HEX_EVEN: [(isize, isize); 6] = [(-1, 0), (-1, -1), (0, -1), (1, 0), (0, 1), (-1, 1)];
+const HEX_ODD: [(isize, isize); 6] = [(-1, 0), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1)];
+
+impl Grid {
+  /// The (dx, dy) from a cell in row `y` to each of its neighbors.
+  pub(crate) fn offsets(self, y: isize) -> &'static [(isize, isize)] {
+    match self {
+      Grid::Square => &SQUARE,
+      Grid::Diagonal => &DIAGONAL,
+      Grid::Hex if y.rem_euclid(2) == 0 => &HEX_EVEN,
+      Grid::Hex => &HEX_ODD,
+    }
+  }
+
+  /// Whether every cell is a neighbor of its neighbors. A hex grid wrapped top to
+  /// bottom with an odd height puts the last row against another odd row, which
+  /// shifts the wrong way.
+  pub(crate) fn fits(self, topology: Topology, height: usize) -> bool {
+    !(self == Grid::Hex && topology == Topology::Torus && height % 2 == 1)
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Like `set_topology`, this should be set before the first `spill`. Returns
+  /// false and leaves the grid alone if it doesn't fit the board, see `Grid::Hex`.
+  pub fn set_grid(&mut self, grid: Grid) -> bool {
+    if !grid.fits(self.topology, self.height) {
+      return false;
+    }
+    if grid != self.grid {
+      self.replay.push(super::Input::SetGrid(grid));
+      self.grid = grid;
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Point;
+
+  #[test]
+  fn hex_torus_needs_an_even_height() {
+    let mut board = Board::with_players(6, 3, true, 2);
+    assert!(board.set_topology(Topology::Torus));
+    assert!(!board.set_grid(Grid::Hex));
+    let mut board = Board::with_players(6, 3, true, 2);
+    assert!(board.set_grid(Grid::Hex));
+    assert!(!board.set_topology(Topology::Torus));
+  }
+
+  #[test]
+  fn neighbors_go_both_ways() {
+    for grid in [Grid::Square, Grid::Diagonal, Grid::Hex] {
+      for topology in [Topology::Plane, Topology::Torus] {
+        for height in [3, 4] {
+          let mut board = Board::with_players(6, height, true, 2);
+          if !(board.set_grid(grid) && board.set_topology(topology)) {
+            continue;
+          }
+          for idx in 0..board.area() {
+            let pos = Point::new((idx % 6) as isize, (idx / 6) as isize);
+            for neighbor in board.neighbors(pos) {
+              assert!(
+                board.neighbors(neighbor).any(|back| back == pos),
+                "{:?} {:?} {:?} -> {:?}",
+                grid,
+                topology,
+                pos,
+                neighbor
+              );
+            }
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Input};
+
+/// A player's supply of liquid, when it isn't endless. Every cell spilled uses up
+/// one cell of ink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Ink {
+  pub(crate) left: u32,
+  /// The most ink a player can hold, and what they start with.
+  budget: u32,
+  /// Ink given back at the start of every frame.
+  refill: u32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Limit how much `player` can spill: they start with `budget` cells of ink and
+  /// get `refill` more every frame, up to `budget` again. A refill of 0 makes it a
+  /// fixed total for the whole game. Once they're out, their spigot does nothing.
+  pub fn set_ink(&mut self, player: usize, budget: u32, refill: u32) {
+    self.replay.push(Input::SetInk { player, ink: Some(Ink { left: budget, budget, refill }) });
+    self.players[player].ink = Some(Ink { left: budget, budget, refill });
+  }
+
+  /// Let `player` spill as much as they like again, which is the default.
+  pub fn remove_ink_limit(&mut self, player: usize) {
+    self.replay.push(Input::SetInk { player, ink: None });
+    self.players[player].ink = None;
+  }
+}
+
+impl Board {
+  /// Called by `spill` at the start of every frame.
+  pub(crate) fn refill_ink(&mut self) {
+    for ink in self.players.iter_mut().filter_map(|player| player.ink.as_mut()) {
+      ink.left = ink.left.saturating_add(ink.refill).min(ink.budget);
+    }
+  }
+
+  /// Whether `player` is out of ink.
+  pub(crate) fn dry(&self, player: usize) -> bool {
+    self.players[player].ink.is_some_and(|ink| ink.left == 0)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{Point, SpillResult};
+
+  fn spill(board: &mut Board, frames: usize) -> SpillResult {
+    let spigots = [Some(Point::new(3, 3)), Some(Point::new(12, 12))];
+    let mut result = None;
+    for frame in 0..frames {
+      result = Some(board.spill_players(&spigots, 4, frame % 2));
+    }
+    result.unwrap()
+  }
+
+  #[test]
+  fn running_out_of_ink_stops_the_spigot() {
+    let mut board = Board::new(16, true);
+    board.set_ink(0, 5, 0);
+    let result = spill(&mut board, 10);
+    assert_eq!((result.ink(0), result.ink(1)), (Some(0), None));
+    let score = board.score();
+    assert_eq!(score.stone(0), 5);
+    assert!(score.stone(1) > 5);
+  }
+
+  #[test]
+  fn ink_refills_every_frame() {
+    let mut board = Board::new(16, true);
+    board.set_ink(0, 5, 1);
+    spill(&mut board, 10);
+    let stones = board.score().stone(0);
+    let result = spill(&mut board, 3);
+    assert_eq!(result.ink(0), Some(0));
+    assert_eq!(board.score().stone(0), stones + 3);
+  }
+}
@@ -1,12 +1,48 @@
 // liquid_go, written by Aaron Kriegman <aaronkplus2@gmail.com>
 
 use rstar::RTree;
+use serde::{Deserialize, Serialize};
 use union_find::{QuickFindUf as UF, Union, UnionFind, UnionResult};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 use console::debug;
-use Team::*;
+use fixed::Fixed;
+use ink::Ink;
+use replay::Input;
+use rollback::Rollback;
+use spigot::Spigot;
+mod arena;
+mod bot;
+mod checksum;
+mod dead;
+mod estimate;
+mod fixed;
+mod grid;
+mod ink;
+mod replay;
+mod rollback;
+mod rollout;
+mod schedule;
 mod score;
+mod setup;
+mod snapshot;
+mod spigot;
+mod topology;
+mod tournament;
+mod wall;
+
+pub use arena::{play_match, strategy, MatchConfig, MatchResult, STRATEGIES};
+pub use bot::{Aim, BodyInfo, Bot, Cutter, Defender, Expander, Strategy, View};
+pub use estimate::Estimate;
+pub use grid::Grid;
+pub use replay::Replay;
+pub use rollout::Rollout;
+pub use schedule::Schedule;
+pub use score::{Region, RegionKind, ScoreResult, Scoring};
+pub use spigot::Sharing;
+pub use topology::Topology;
+pub use tournament::Tournament;
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
@@ -14,8 +50,10 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 // #[cfg(not)]
 mod console {
+  #[cfg(feature = "wasm")]
   use super::wasm_bindgen;
 
+  #[cfg(feature = "wasm")]
   #[wasm_bindgen]
   extern "C" {
     #[wasm_bindgen(js_namespace = console, js_name = log)]
@@ -26,6 +64,13 @@ mod console {
     pub fn time_end(s: &str);
   }
 
+  /// Without a JS console to talk to we just print to stderr.
+  #[cfg(not(feature = "wasm"))]
+  pub fn console_log(s: &str) {
+    eprintln!("{}", s);
+  }
+
+  #[allow(unused_macros)]
   macro_rules! log {
     ($($t:tt)*) => (crate::console::console_log(&format_args!($($t)*).to_string()))
   }
@@ -34,6 +79,7 @@ mod console {
   }
 
   pub(crate) use debug;
+  #[allow(unused_imports)]
   pub(crate) use log;
 }
 
@@ -44,32 +90,56 @@ mod console {
 //   console_error_panic_hook::set_once();
 // }
 
-#[wasm_bindgen]
+/// The most players a board can have, so that a set of players fits in a u64.
+pub const MAX_PLAYERS: usize = 64;
+
+/// The fastest a spigot can chase where it's aimed. Any faster and it overshoots
+/// further every step instead of settling down.
+const MAX_SPEED: f32 = 2.;
+
+/// The color of empty board, as a little endian RGBA color.
+const EMPTY_COLOR: u32 = 0x_ff_48_9b_bb;
+
+/// The color of walls.
+const WALL_COLOR: u32 = 0x_ff_50_50_50;
+
+/// The colors players start with, in order. Black and White come first so that
+/// two player games look the way they always have.
+const PLAYER_COLORS: [u32; 8] = [
+  0x_ff_20_00_00,
+  0x_ff_e0_ff_ff,
+  0x_ff_30_30_d0,
+  0x_ff_e0_60_30,
+  0x_ff_40_a0_30,
+  0x_ff_b0_30_90,
+  0x_ff_10_90_f0,
+  0x_ff_a0_a0_20,
+];
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone)]
 pub struct Board {
   // OPTIONS, INPUT, AND STATE ---------------------------------------------
-  b_pos: Option<[f32; 2]>,
-  b_tail: Option<[f32; 2]>,
-  b_speed: f32,
-  w_pos: Option<[f32; 2]>,
-  w_tail: Option<[f32; 2]>,
-  w_speed: f32,
+  /// Black is player 0 and White is player 1.
+  players: Vec<Player>,
 
   capturing: bool,
-  /// Side length.
-  size: usize,
-
-  b_prisoners: isize,
-  w_prisoners: isize,
+  width: usize,
+  height: usize,
+  topology: Topology,
+  grid: Grid,
+  sharing: Sharing,
+  schedule: Schedule,
 
   // INTERNALS -------------------------------------------------------------
 
-  // The board data is split into two separate
-  // Vecs instead of one Vec of structs so that
-  // hopefully the data will need no additional
-  // processing when drawn to the canvas. `team`
-  // serves both as the board state and as the
-  // iamge to draw, since a `Team` is also a color.
+  // The board data is split into separate Vecs
+  // instead of one Vec of structs so that `image`
+  // will need no additional processing when drawn
+  // to the canvas. `set_team` keeps it in step
+  // with `teams`.
   teams: Vec<Team>,
+  image: Vec<u32>,
   /// owner[i] is the index of the Body in bodies
   /// that cell i belongs to.
   owner: Vec<usize>,
@@ -77,8 +147,20 @@ pub struct Board {
   bodies: UF<Body>,
   /// An alternate screenbuffer. Used in debugging and showing territory.
   alt_image: Vec<u32>,
+  /// Who `estimate` thinks owns each cell, for drawing over the board. Empty until
+  /// the first `estimate`.
+  heatmap: Vec<u32>,
+  /// Every input since `new`, so the game can be replayed.
+  replay: Replay,
+  /// The XOR of `checksum::cell_key` over every cell, kept up to date as cells change.
+  teams_hash: u64,
+  /// checksums[i] is the `checksum` right after frame i.
+  checksums: Vec<u32>,
+  /// Recent copies of the board, so we can `rewind`.
+  rollback: Rollback,
 }
 
+#[derive(Clone)]
 struct TreeParams {}
 
 impl rstar::RTreeParams for TreeParams {
@@ -93,7 +175,7 @@ impl rstar::RTreeParams for TreeParams {
 /// added through these two methods, and Body is only constructed from duplicate
 /// free lists, then we can assume the invariant that Body is duplicate free.
 /// Reading and removing points can be done with the underlying RTree safely.
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct Body {
   libs: RTree<Point, TreeParams>,
   /// A list of intersections in this body that would be liberties of another body,
@@ -102,43 +184,101 @@ struct Body {
   /// For use when reinserting liberties like above, so that we don't give liberties
   /// to a dead body.
   alive: bool,
+  /// Marked dead by the players at the end of the game, see `toggle_dead`.
+  marked: bool,
   team: Team,
 }
 
-/// These numbers are meant to be converted to little endian RGBA colors.
-#[repr(u32)]
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-enum Team {
-  Black = 0x_ff_20_00_00,
-  White = 0x_ff_e0_ff_ff,
-  Empty = 0x_ff_48_9b_bb,
+/// One player's spigots, along with their captures.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Player {
+  /// Everywhere they've spilled from at once, most players only ever use the
+  /// first. These are never removed, so that the rest keep their places.
+  spigots: Vec<Spigot>,
+  /// How fast their spigots chase where they are aimed, 1 by default.
+  speed: Fixed,
+  /// Their share of the turns under `Schedule::Weighted`.
+  weight: Fixed,
+  /// Cells of other players' liquid this player has captured.
+  prisoners: isize,
+  /// Cells of this player's liquid that have been captured.
+  lost: isize,
+  /// Extra points at the end of the game, see `set_komi`.
+  komi: f32,
+  /// None for endless liquid, see `set_ink`.
+  ink: Option<Ink>,
+  /// A little endian RGBA color.
+  color: u32,
 }
 
-#[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Whose liquid is on a cell, as an index into `Board::players`. Only a byte, so
+/// that `teams` stays small.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+struct Team(u8);
+
+/// What `spill` did. The b_ and w_ fields are players 0 and 1, for two player
+/// games. Use the methods for any player.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct SpillResult {
+  /// Prisoners taken so far.
+  pub b_prisoners: isize,
+  pub w_prisoners: isize,
+  prisoners: Vec<isize>,
+  ink: Vec<Option<u32>>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SpillResult {
+  /// Cells of other players' liquid `player` has captured so far.
+  pub fn prisoners(&self, player: usize) -> isize {
+    self.prisoners[player]
+  }
+
+  /// The ink `player` has left, or None if they have no limit. See `set_ink`.
+  pub fn ink(&self, player: usize) -> Option<u32> {
+    self.ink[player]
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Point {
   pub x: isize,
   pub y: isize,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Board {
+  /// A square two player board, Black against White.
   pub fn new(size: usize, capturing: bool) -> Self {
+    Self::with_players(size, size, capturing, 2)
+  }
+
+  /// A `width` by `height` board for any number of players from 1 up to
+  /// `MAX_PLAYERS`.
+  pub fn with_players(width: usize, height: usize, capturing: bool, players: usize) -> Self {
+    assert!(players >= 1, "a board needs at least one player");
+    assert!(players <= MAX_PLAYERS, "at most {} players are supported", MAX_PLAYERS);
+    let area = width * height;
     Self {
-      b_pos: None,
-      b_tail: None,
-      b_speed: 1.,
-      w_pos: None,
-      w_tail: None,
-      w_speed: 1.,
+      players: (0..players).map(Player::new).collect(),
       capturing,
-      size,
-      b_prisoners: 0,
-      w_prisoners: 0,
-      teams: vec![Empty; size * size],
-      owner: vec![0; size * size],
+      width,
+      height,
+      topology: Topology::Plane,
+      grid: Grid::Square,
+      sharing: Sharing::Split,
+      schedule: Schedule::Snake,
+      teams: vec![Team::EMPTY; area],
+      image: vec![EMPTY_COLOR; area],
+      owner: vec![0; area],
       bodies: UF::new(1),
-      alt_image: vec![0; size * size],
+      alt_image: vec![0; area],
+      heatmap: vec![],
+      replay: Replay::new(width, height, capturing, players),
+      teams_hash: 0,
+      checksums: vec![],
+      rollback: Rollback::default(),
     }
   }
 
@@ -150,15 +290,19 @@ impl Board {
   pub fn get_image_slice(&self, buffer: u8) -> Point {
     match buffer {
       0 => Point {
-        x: self.teams.as_ptr() as isize,
-        y: self.teams.len() as isize * 4, // sizeof u32 / sizeof u8 = 4
+        x: self.image.as_ptr() as isize,
+        y: self.image.len() as isize * 4, // sizeof u32 / sizeof u8 = 4
       },
       1 => Point {
         x: self.alt_image.as_ptr() as isize,
         y: self.alt_image.len() as isize * 4, // sizeof u32 / sizeof u8 = 4
       },
+      2 => Point {
+        x: self.heatmap.as_ptr() as isize,
+        y: self.heatmap.len() as isize * 4, // sizeof u32 / sizeof u8 = 4
+      },
       _ => {
-        debug!("buffer should be 0 for the main board or 1 for the alternate board. Treat it like an enum.");
+        debug!("buffer should be 0 for the main board, 1 for the alternate board or 2 for the heatmap. Treat it like an enum.");
         panic!();
       }
     }
@@ -188,20 +332,67 @@ impl Board {
   }
 
   pub fn set_capturing(&mut self, capturing: bool) {
-    self.capturing = capturing;
+    if capturing != self.capturing {
+      self.replay.push(Input::SetCapturing(capturing));
+      self.capturing = capturing;
+    }
   }
 
-  pub fn set_speed(&mut self, black: bool, speed: f32) {
-    if black {
-      self.b_speed = speed;
-    } else {
-      self.w_speed = speed;
+  /// Set Black's speed if `black`, otherwise White's.
+  pub fn set_speed(&mut self, black: bool, speed: f32) -> bool {
+    self.set_player_speed(if black { 0 } else { 1 }, speed)
+  }
+
+  /// How fast `player`'s spigots chase where they're aimed, 1 by default. Speeds
+  /// are kept between 0 and `MAX_SPEED`, and this returns false and changes
+  /// nothing if `speed` isn't a number.
+  pub fn set_player_speed(&mut self, player: usize, speed: f32) -> bool {
+    if !speed.is_finite() {
+      return false;
+    }
+    let fixed = Fixed::from_f32(speed.clamp(0., MAX_SPEED));
+    if fixed != self.players[player].speed {
+      self.replay.push(Input::SetSpeed { player, speed });
+      self.players[player].speed = fixed;
     }
+    true
+  }
+
+  /// `color` is a little endian RGBA color.
+  pub fn set_color(&mut self, player: usize, color: u32) {
+    self.players[player].color = color;
+    let team = Team(player as u8);
+    for (pixel, _) in self.image.iter_mut().zip(&self.teams).filter(|(_, &t)| t == team) {
+      *pixel = color;
+    }
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn players(&self) -> usize {
+    self.players.len()
+  }
+
+  /// The number of other players' cells `player` has captured.
+  pub fn prisoners(&self, player: usize) -> isize {
+    self.players[player].prisoners
+  }
+
+  /// A copy of every input this board has received, for reproducing the game.
+  /// Not for a `fork`, whose replay starts at the fork and can't be played.
+  pub fn replay(&self) -> Replay {
+    self.replay.clone()
   }
 
   #[inline]
   fn get_idx(&self, p: Point) -> usize {
-    p.x as usize + p.y as usize * self.size
+    p.x as usize + p.y as usize * self.width
   }
 
   #[inline]
@@ -209,10 +400,21 @@ impl Board {
     self.teams[self.get_idx(p)]
   }
 
+  /// The only way cells should change hands, so that `image` and `teams_hash`
+  /// stay in step with `teams`.
   #[inline]
-  fn get_teams_mut(&mut self, p: Point) -> &mut Team {
-    let idx = self.get_idx(p);
-    &mut self.teams[idx]
+  fn set_team(&mut self, idx: usize, team: Team) {
+    self.teams_hash ^= checksum::cell_key(idx, self.teams[idx]) ^ checksum::cell_key(idx, team);
+    self.teams[idx] = team;
+    self.image[idx] = self.color(team);
+  }
+
+  #[inline]
+  fn color(&self, team: Team) -> u32 {
+    match team {
+      Team::WALL => WALL_COLOR,
+      _ => team.player().map_or(EMPTY_COLOR, |player| self.players[player].color),
+    }
   }
 
   #[inline]
@@ -228,26 +430,11 @@ impl Board {
 
   #[inline]
   fn area(&self) -> usize {
-    self.size * self.size
+    self.width * self.height
   }
 
-  /// Pseudo code:
-  /// ```
-  /// for each player:
-  ///   if mouse is not down or is down over enemy cells:
-  ///     this player does nothing this frame.
-  ///   else:
-  ///     if mouse is over empty space:
-  ///       player.current_body = new body
-  ///     else:
-  ///       player.current_body = the body at this position
-  ///
-  /// for count / 2 times:
-  ///   first player spills if they're active this frame
-  ///   second player spills ---
-  ///   second player spills ---
-  ///   first player spills ---
-  /// ```
+  /// Spill for Black and White. This is `spill_players` for two players, where
+  /// the caller should flip `black_first` every frame.
   pub fn spill(
     &mut self,
     b_pos: &Point,
@@ -256,165 +443,103 @@ impl Board {
     w_active: bool,
     count: u32,
     black_first: bool,
-  ) -> Point {
+  ) -> SpillResult {
     let b_pos: Option<Point> = if b_active { Some(*b_pos) } else { None };
     let w_pos: Option<Point> = if w_active { Some(*w_pos) } else { None };
-    let mut b_key: Option<usize> = None;
-    let mut w_key: Option<usize> = None;
-    if self.b_pos == None || b_pos == None {
-      self.b_pos = b_pos.map(Point::as_f32);
-      self.b_tail = self.b_pos;
-    };
-    if self.w_pos == None || w_pos == None {
-      self.w_pos = w_pos.map(Point::as_f32);
-      self.w_tail = self.w_pos;
-    };
-
-    // Summary of the ugly spaghetti logic:
-    // If mouse is not down or we are over enemy territory, p_pos = None and p_key =
-    // 0. Otherwise set p_key to the existing body if over our own territory,
-    // or a new body if over empty board. Do this for both players.
-    // I claim that after this block, p_key will be non-zero iff p_pos is non-None.
-    for (pos, key_ref, team) in [(self.b_pos, &mut b_key, Black), (self.w_pos, &mut w_key, White)] {
-      if let Some(pos) = pos.map(Point::from_f32) {
-        match (self.get_teams(pos), team) {
-          (Black, White) | (White, Black) => {
-            if let Some(&near) = self.bodies.get(self.get_owner(pos)).libs.nearest_neighbor(&pos) {
-              *key_ref = Some(self.bodies.insert(Body::new(vec![near], team)))
-            }
-          }
-          (Black, Black) | (White, White) => {
-            *key_ref = Some(self.get_owner(pos));
-          }
-          (Empty, _) => *key_ref = Some(self.bodies.insert(Body::new(vec![pos], team))),
-          (_, Empty) => unreachable!(),
-        }
-      }
-    }
-
-    // vroom = 2 (or vroom close to 2) causes problems, hence the max.
-    let b_vroom = self.b_speed / count.max(2) as f32;
-    let w_vroom = self.w_speed / count.max(2) as f32;
-    for _ in (0..count).step_by(2) {
-      for tup in [
-        (&mut self.b_tail, &mut self.b_pos, b_pos, b_vroom),
-        (&mut self.w_tail, &mut self.w_pos, w_pos, w_vroom),
-      ] {
-        if let (Some(self_tail), Some(self_pos), Some(pos), vroom) = tup {
-          self_tail[0] += (pos.x as f32 - self_tail[0]) * vroom;
-          self_tail[1] += (pos.y as f32 - self_tail[1]) * vroom;
-          self_pos[0] += (self_tail[0] - self_pos[0]) * vroom;
-          self_pos[1] += (self_tail[1] - self_pos[1]) * vroom;
-        }
-      }
-
-      // for (pos, key) in [(self.b_pos, &mut b_key), (self.w_pos, &mut w_key)] {
-      //   if let (Some(pos), Some(key)) = (pos.map(Point::from_f32), key) {
-      //     if self.get_teams(pos) == Empty && (i / 2) % (count / 8) == (count / 16) {
-      //       *key = self
-      //         .bodies
-      //         .insert(Body { libs: RTree::bulk_load_with_params(vec![pos]), ..Body::default() })
-      //     }
-      //   }
-      // }
-
-      // What I'm really trying to do here is irreducible control flow.
-      let (f_pos, f_key, f_team, s_pos, s_key, s_team) = if black_first {
-        (self.b_pos, &mut b_key, Black, self.w_pos, &mut w_key, White)
-      } else {
-        (self.w_pos, &mut w_key, White, self.b_pos, &mut b_key, Black)
-      };
-      let f_pos = f_pos.map(Point::from_f32);
-      let s_pos = s_pos.map(Point::from_f32);
-      // I could technically remove these if statements because the if statement in
-      // assimilate will fail for the 0th Body. TODO check if this makes a
-      // difference.
-      if let (Some(pos), Some(key)) = (f_pos, *f_key) {
-        if self.assimilate(pos, key, f_team) {
-          *f_key = None;
-        }
-      }
-      if let (Some(pos), Some(key)) = (s_pos, *s_key) {
-        if self.assimilate(pos, key, s_team) {
-          *s_key = None;
-        }
-        if self.assimilate(pos, key, s_team) {
-          *s_key = None;
-        }
-      }
-      if let (Some(pos), Some(key)) = (f_pos, *f_key) {
-        if self.assimilate(pos, key, f_team) {
-          *f_key = None;
-        }
-      }
-    }
-
-    Point::new(self.b_prisoners, self.w_prisoners)
+    self.spill_players(&[b_pos, w_pos], count, if black_first { 0 } else { 1 })
   }
 
+  /// `spill_players` flattened for crossing the js-wasm barrier. Player i's
+  /// spigot is at (xs[i], ys[i]) and is on if active[i] != 0.
+  pub fn spill_all(
+    &mut self,
+    xs: &[i32],
+    ys: &[i32],
+    active: &[u8],
+    count: u32,
+    first: usize,
+  ) -> SpillResult {
+    let spigots: Vec<Option<Point>> = (0..self.players.len())
+      .map(|i| if active[i] != 0 { Some(Point::new(xs[i] as isize, ys[i] as isize)) } else { None })
+      .collect();
+    self.spill_players(&spigots, count, first)
+  }
   /// Tell bodies[bod_key] to absorb it's nearest liberty. Returns true if there's no
   fn assimilate(&mut self, spigot: Point, bod_key: usize, us: Team) -> bool {
+    debug_assert!(us != Team::EMPTY, "You cannot assimilate back into the board.");
     // TODO: some of these `bodies.get` calls should be merged, since
     // `get` is not a simple read on a UnionFind.
-    if let Some(pos) = self.bodies.get_mut(bod_key).libs.pop_nearest_neighbor(&spigot) {
-      if self.get_teams(pos) != Empty {
-        // panic!("Liberties should always be empty board.");
-        // This case should only occur when two bodies are initiated
-        // at the same position on the same frame. We do not panic so
-        // we can handle this case gracefully.
-        return true;
-      }
-
-      *self.get_teams_mut(pos) = us;
-      *self.get_owner_mut(pos) = bod_key;
-      // Neighboring bodies to be `check_dead`ed at the end of this function.
-      let mut neighbors = Vec::new();
-
-      for lib in [
-        if pos.x > 0 { Some(Point { x: pos.x - 1, ..pos }) } else { None },
-        if pos.y > 0 { Some(Point { y: pos.y - 1, ..pos }) } else { None },
-        if pos.x < self.size as isize - 1 { Some(Point { x: pos.x + 1, ..pos }) } else { None },
-        if pos.y < self.size as isize - 1 { Some(Point { y: pos.y + 1, ..pos }) } else { None },
-      ]
-      .into_iter()
-      .flatten()
-      {
-        match (us, self.get_teams(lib)) {
-          (Black, White) | (White, Black) => {
-            let other_key = self.get_owner(lib);
-            let other = self.bodies.get_mut(other_key);
-            other.libs.remove(&pos);
-            other.stollen_libs.push((lib, bod_key));
-            self.bodies.get_mut(bod_key).stollen_libs.push((pos, other_key));
-            neighbors.push(other_key);
-          }
-          (Black, Black) | (White, White) => {
-            self.bodies.union(bod_key, self.get_owner(lib));
-            self.bodies.get_mut(bod_key).libs.remove(&pos);
-          }
-
-          (_, Empty) => {
-            self.bodies.get_mut(bod_key).insert(lib);
-          }
-          (Empty, _) => unreachable!("You cannot assimilate back into the board."),
-        }
-      }
-
-      // Check if opponent is captured first.
-      for neighbor in neighbors {
-        self.check_dead(neighbor);
-      }
-      self.check_dead(bod_key)
+    let (topology, width, height) = (self.topology, self.width, self.height);
+    if let Some(pos) =
+      topology.pop_nearest_lib(&mut self.bodies.get_mut(bod_key).libs, spigot, width, height)
+    {
+      self.absorb(pos, bod_key, us)
     } else {
       // panic!("Assimilate should never be called on a body with no liberties.");
       // This case only occurs when capturing is disabled then reenabled. We will not panic
       // here so we can handle this case gracefully.
-      self.check_dead(bod_key)
+      self.check_dead(bod_key, us)
     }
   }
 
-  /// Check if `bod` is dead, and if so remove it and return true.
-  fn check_dead(&mut self, bod_key: usize) -> bool {
+  /// The rest of `assimilate`, once the liberty `pos` has been taken out of
+  /// bodies[bod_key].
+  fn absorb(&mut self, pos: Point, bod_key: usize, us: Team) -> bool {
+    if self.get_teams(pos) != Team::EMPTY {
+      // panic!("Liberties should always be empty board.");
+      // This case should only occur when two bodies are initiated
+      // at the same position on the same frame. We do not panic so
+      // we can handle this case gracefully.
+      return true;
+    }
+
+    let neighbors = self.fill(pos, bod_key, us);
+    if let Some(ink) = &mut self.players[us.0 as usize].ink {
+      ink.left -= 1;
+    }
+
+    // Check if opponent is captured first.
+    for neighbor in neighbors {
+      self.check_dead(neighbor, us);
+    }
+    self.check_dead(bod_key, us)
+  }
+
+  /// Put `us` on the empty cell `pos` as part of bodies[bod_key], and hook it up
+  /// to the cells around it: merge with our liquid, take liberties from everyone
+  /// else's. Returns the neighboring enemy bodies, which might be dead now.
+  fn fill(&mut self, pos: Point, bod_key: usize, us: Team) -> Vec<usize> {
+    self.set_team(self.get_idx(pos), us);
+    *self.get_owner_mut(pos) = bod_key;
+    let mut neighbors = Vec::new();
+
+    for lib in self.neighbors(pos) {
+      match self.get_teams(lib) {
+        Team::EMPTY => {
+          self.bodies.get_mut(bod_key).insert(lib);
+        }
+        Team::WALL => (),
+        them if them == us => {
+          self.bodies.union(bod_key, self.get_owner(lib));
+          self.bodies.get_mut(bod_key).libs.remove(&pos);
+        }
+        _ => {
+          let other_key = self.get_owner(lib);
+          let other = self.bodies.get_mut(other_key);
+          other.libs.remove(&pos);
+          other.stollen_libs.push((lib, bod_key));
+          self.bodies.get_mut(bod_key).stollen_libs.push((pos, other_key));
+          neighbors.push(other_key);
+        }
+      }
+    }
+    neighbors
+  }
+
+  /// Check if `bod` is dead, and if so remove it and return true. `killer` is the
+  /// team whose move might have killed it, and gets the prisoners. Nobody does if
+  /// it was `Team::WALL`.
+  fn check_dead(&mut self, bod_key: usize, killer: Team) -> bool {
     if !self.capturing {
       return false;
     }
@@ -425,18 +550,24 @@ impl Board {
       bod.alive = false;
 
       let mut prisoners = 0;
-      self.teams.iter_mut().zip(self.owner.iter_mut()).for_each(|(t, o)| {
-        if self.bodies.find(*o) == self.bodies.find(bod_key) {
-          *t = Empty;
-          *o = 0;
+      let root = self.bodies.find(bod_key);
+      for idx in 0..self.area() {
+        if self.bodies.find(self.owner[idx]) == root {
+          self.set_team(idx, Team::EMPTY);
+          self.owner[idx] = 0;
           prisoners += 1;
         }
-      });
+      }
 
-      match corpse.team {
-        Black => self.w_prisoners += prisoners,
-        White => self.b_prisoners += prisoners,
-        Empty => debug!("It should not be possible to capture a body of empty board."),
+      match corpse.team.player() {
+        Some(player) => {
+          let captor = if killer != corpse.team { killer } else { self.captor(&corpse) };
+          self.players[player].lost += prisoners;
+          if let Some(captor) = captor.player() {
+            self.players[captor].prisoners += prisoners;
+          }
+        }
+        None => debug!("It should not be possible to capture a body of empty board."),
       }
 
       for (lib, other_key) in corpse.stollen_libs {
@@ -450,11 +581,213 @@ impl Board {
       false
     }
   }
+
+  /// Who gets the prisoners when a body fills its own last liberty: whoever it
+  /// touches the most. If it touches nobody, only walls and the edge of the board
+  /// took its liberties, so it's `Team::WALL` and nobody gets them.
+  fn captor(&mut self, corpse: &Body) -> Team {
+    let mut touching = vec![0; self.players.len()];
+    for &(_, other_key) in &corpse.stollen_libs {
+      if let Some(other) = self.bodies.get(other_key).team.player() {
+        touching[other] += 1;
+      }
+    }
+    touching[corpse.team.0 as usize] = 0;
+    match touching
+      .iter()
+      .enumerate()
+      .max_by_key(|&(player, &count)| (count, std::cmp::Reverse(player)))
+    {
+      Some((player, &count)) if count > 0 => Team(player as u8),
+      _ => Team::WALL,
+    }
+  }
+}
+
+impl Board {
+  /// Pseudo code:
+  /// ```text
+  /// for each player:
+  ///   if mouse is not down or is down over enemy cells:
+  ///     this player does nothing this frame.
+  ///   else:
+  ///     if mouse is over empty space:
+  ///       player.current_body = new body
+  ///     else:
+  ///       player.current_body = the body at this position
+  ///
+  /// for count / 2 times:
+  ///   every player spills about twice if they're active this frame, in the
+  ///   order given by the `Schedule`, starting from `first`
+  /// ```
+  /// With two players and the default `Schedule::Snake` that's first, second,
+  /// second, first. Callers should move `first` on to the next player every frame
+  /// so that nobody is always first.
+  /// `spigots` has one entry per player, None if their mouse is not down.
+  /// Returns the prisoners taken and the ink left by each player.
+  pub fn spill_players(
+    &mut self,
+    spigots: &[Option<Point>],
+    count: u32,
+    first: usize,
+  ) -> SpillResult {
+    assert_eq!(spigots.len(), self.players.len(), "spill_players needs one spigot per player");
+    let spigots: Vec<Vec<Option<Point>>> = spigots.iter().map(|&spigot| vec![spigot]).collect();
+    self.spill_spigots(&spigots, count, first)
+  }
+
+  /// `spill_players` where each player can spill from several places at once.
+  /// spigots[p][i] is where player p's i-th spigot is aimed, or None if it's off.
+  /// Keep each spigot at the same index from frame to frame so that it carries on
+  /// from where it was, and leave off the ends of the lists any spigots that
+  /// aren't in use. How much each one spills depends on `set_sharing`.
+  pub fn spill_spigots(
+    &mut self,
+    spigots: &[Vec<Option<Point>>],
+    count: u32,
+    first: usize,
+  ) -> SpillResult {
+    assert_eq!(spigots.len(), self.players.len(), "spill_spigots needs a list for every player");
+    self.save_rollback();
+    self.replay.push(Input::Spill { spigots: spigots.to_vec(), count, first });
+    self.refill_ink();
+    for (player, targets) in self.players.iter_mut().zip(spigots) {
+      if player.spigots.len() < targets.len() {
+        player.spigots.resize(targets.len(), Spigot::default());
+      }
+      for (i, spigot) in player.spigots.iter_mut().enumerate() {
+        spigot.aim(targets.get(i).copied().flatten());
+      }
+    }
+
+    // Summary of the ugly spaghetti logic:
+    // If mouse is not down or we are over a wall, key = None.
+    // Otherwise set key to the existing body if over our own territory,
+    // or a new body if over empty board. Do this for every spigot.
+    // I claim that after this block, key will be Some iff pos is Some.
+    let mut keys: Vec<Vec<Option<usize>>> =
+      self.players.iter().map(|player| vec![None; player.spigots.len()]).collect();
+    for (player, spigot_keys) in keys.iter_mut().enumerate() {
+      let team = Team(player as u8);
+      for (i, key_ref) in spigot_keys.iter_mut().enumerate() {
+        if let Some(pos) = self.players[player].spigots[i].pos.map(Point::from_fixed) {
+          match self.get_teams(pos) {
+            Team::EMPTY => *key_ref = Some(self.bodies.insert(Body::new(vec![pos], team))),
+            Team::WALL => (),
+            them if them == team => {
+              *key_ref = Some(self.get_owner(pos));
+            }
+            _ => {
+              let (topology, width, height) = (self.topology, self.width, self.height);
+              let libs = &self.bodies.get(self.get_owner(pos)).libs;
+              if let Some(near) = topology.nearest_lib(libs, pos, width, height) {
+                *key_ref = Some(self.bodies.insert(Body::new(vec![near], team)))
+              }
+            }
+          }
+        }
+      }
+    }
+
+    // vroom = 2 (or vroom close to 2) causes problems, hence the max.
+    let vrooms: Vec<Fixed> =
+      self.players.iter().map(|player| player.speed.div_int(count.max(2))).collect();
+    let weights: Vec<Fixed> = self.players.iter().map(|player| player.weight).collect();
+    // Turns owed under `Schedule::Weighted`.
+    let mut credits = vec![Fixed::default(); self.players.len()];
+    // With `Sharing::Split`, which of their spigots each player spills from next.
+    let mut turns = vec![0; self.players.len()];
+    for (step, _) in (0..count).step_by(2).enumerate() {
+      for ((player, targets), vroom) in self.players.iter_mut().zip(spigots).zip(&vrooms) {
+        for (spigot, target) in player.spigots.iter_mut().zip(targets) {
+          if let Some(target) = *target {
+            spigot.chase(target, *vroom);
+          }
+        }
+      }
+
+      for round in self.schedule.rounds(step, first, &weights, &mut credits) {
+        let moves: Vec<(usize, usize)> = round
+          .into_iter()
+          .flat_map(|player| {
+            let taking = self.sharing.taking(&keys[player], &mut turns[player]);
+            taking.into_iter().map(move |i| (player, i))
+          })
+          .collect();
+        if self.schedule == Schedule::Simultaneous {
+          for (player, i) in self.clash(&moves, &keys) {
+            keys[player][i] = None;
+          }
+          continue;
+        }
+        for (player, i) in moves {
+          if self.dry(player) {
+            continue;
+          }
+          // I could technically remove these if statements because the if statement in
+          // assimilate will fail for the 0th Body. TODO check if this makes a
+          // difference.
+          if let (Some(pos), Some(key)) =
+            (self.players[player].spigots[i].pos.map(Point::from_fixed), keys[player][i])
+          {
+            if self.assimilate(pos, key, Team(player as u8)) {
+              keys[player][i] = None;
+            }
+          }
+        }
+      }
+    }
+
+    self.checksums.push(self.checksum());
+    SpillResult {
+      b_prisoners: self.players.first().map_or(0, |player| player.prisoners),
+      w_prisoners: self.players.get(1).map_or(0, |player| player.prisoners),
+      prisoners: self.players.iter().map(|player| player.prisoners).collect(),
+      ink: self.players.iter().map(|player| player.ink.map(|ink| ink.left)).collect(),
+    }
+  }
+}
+
+impl Player {
+  fn new(player: usize) -> Self {
+    Player {
+      spigots: vec![Spigot::default()],
+      speed: Fixed::from_int(1),
+      weight: Fixed::from_int(1),
+      prisoners: 0,
+      lost: 0,
+      komi: 0.,
+      ink: None,
+      color: PLAYER_COLORS[player % PLAYER_COLORS.len()],
+    }
+  }
+}
+
+impl Team {
+  const EMPTY: Team = Team(u8::MAX);
+  /// Not liquid and not empty: never a liberty, and nobody's territory.
+  const WALL: Team = Team(u8::MAX - 1);
+
+  /// The index of this team's player, or None for empty board and walls.
+  #[inline]
+  fn player(self) -> Option<usize> {
+    if (self.0 as usize) < MAX_PLAYERS {
+      Some(self.0 as usize)
+    } else {
+      None
+    }
+  }
 }
 
 impl Body {
   fn new(libs: Vec<Point>, team: Team) -> Self {
-    Body { libs: RTree::bulk_load_with_params(libs), stollen_libs: vec![], alive: true, team }
+    Body {
+      libs: RTree::bulk_load_with_params(libs),
+      stollen_libs: vec![],
+      alive: true,
+      marked: false,
+      team,
+    }
   }
   /// A wrapper around RTree::insert which checks for duplicates,
   /// effectively making an RTreeSet.
@@ -485,17 +818,19 @@ impl Union for Body {
       RTree::bulk_load_with_params(lval.libs.iter().chain(rval.libs.iter()).copied().collect());
 
     lval.stollen_libs.append(&mut rval.stollen_libs);
+    // If play goes on after marking, a dead body that grows is back in play.
+    lval.marked &= rval.marked;
     UnionResult::Left(lval)
   }
 }
 
 impl Default for Body {
   fn default() -> Self {
-    Body::new(vec![], Empty)
+    Body::new(vec![], Team::EMPTY)
   }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Point {
   /// For crossing the js-wasm barrier.
   pub fn new(x: isize, y: isize) -> Self {
@@ -508,12 +843,13 @@ impl Point {
     self.y = y;
   }
 
-  fn as_f32(self) -> [f32; 2] {
-    [self.x as f32, self.y as f32]
+  fn as_fixed(self) -> [Fixed; 2] {
+    [Fixed::from_int(self.x), Fixed::from_int(self.y)]
   }
 
-  fn from_f32(arr: [f32; 2]) -> Point {
-    Point { x: arr[0] as isize, y: arr[1] as isize }
+  /// The cell a spigot at `arr` is over.
+  fn from_fixed(arr: [Fixed; 2]) -> Point {
+    Point { x: arr[0].floor(), y: arr[1].floor() }
   }
 }
 
@@ -563,3 +899,103 @@ mod test {
     }
   }
 } // end mod test
+
+/// Replays, saves, forks and rewinds all promise to spill exactly like the board
+/// they came from, which lockstep multiplayer depends on.
+#[cfg(test)]
+mod lockstep {
+  use super::*;
+
+  const FRAMES: usize = 400;
+
+  /// Spigots wandering around the board, the same every time.
+  fn spigots(board: &Board, frame: usize) -> Vec<Option<Point>> {
+    (0..board.players())
+      .map(|player| {
+        let seed = (frame / 40 * 7 + player * 13) as isize;
+        let on = (frame + player * 11) % 90 < 75;
+        let x = (seed * 5 + frame as isize / 3).rem_euclid(board.width() as isize);
+        let y = (seed * 3 + player as isize * 6).rem_euclid(board.height() as isize);
+        on.then_some(Point::new(x, y))
+      })
+      .collect()
+  }
+
+  fn spill(board: &mut Board, frames: std::ops::Range<usize>) {
+    for frame in frames {
+      let first = frame % board.players();
+      board.spill_players(&spigots(board, frame), 6, first);
+    }
+  }
+
+  fn boards() -> Vec<Board> {
+    let setups = [
+      (Grid::Square, Topology::Plane, 2),
+      (Grid::Hex, Topology::Torus, 3),
+      (Grid::Diagonal, Topology::Torus, 4),
+    ];
+    setups
+      .into_iter()
+      .map(|(grid, topology, players)| {
+        let mut board = Board::with_players(24, 24, true, players);
+        board.set_grid(grid);
+        board.set_topology(topology);
+        for y in 8..16 {
+          board.set_wall(&Point::new(12, y), true);
+        }
+        board.enable_rollback(50, 10);
+        board
+      })
+      .collect()
+  }
+
+  /// Each setup played straight through, with its checksums.
+  fn played() -> Vec<(Board, Vec<u32>)> {
+    boards()
+      .into_iter()
+      .map(|mut board| {
+        spill(&mut board, 0..FRAMES);
+        let checksums = board.checksums(0);
+        (board, checksums)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn replays_match() {
+    for (board, checksums) in played() {
+      assert_eq!(board.replay().play().checksums(0), checksums);
+    }
+  }
+
+  #[test]
+  fn saves_match() {
+    for (mut board, checksums) in boards().into_iter().zip(played().into_iter().map(|p| p.1)) {
+      spill(&mut board, 0..FRAMES / 2);
+      let mut loaded = Board::load(&board.save()).expect("a save loads");
+      spill(&mut loaded, FRAMES / 2..FRAMES);
+      assert_eq!(loaded.checksums(0), checksums);
+    }
+  }
+
+  #[test]
+  fn rewinds_match() {
+    for (mut board, checksums) in played() {
+      assert!(board.rewind(350));
+      assert_eq!(board.frame(), 350);
+      spill(&mut board, 350..FRAMES);
+      assert_eq!(board.checksums(0), checksums);
+    }
+  }
+
+  #[test]
+  fn forks_match() {
+    for (mut board, checksums) in boards().into_iter().zip(played().into_iter().map(|p| p.1)) {
+      spill(&mut board, 0..FRAMES / 2);
+      let mut fork = board.fork();
+      spill(&mut fork, FRAMES / 2..FRAMES);
+      // A fork starts counting frames from 0.
+      assert_eq!(fork.checksums(0), checksums[FRAMES / 2..]);
+    }
+  }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Grid, Ink, Point, Schedule, Sharing, Topology};
+
+/// Everything that happens to a `Board` after `Board::new`, in order. `spill`
+/// is a pure function of its arguments and the board, so this is all we need
+/// to rebuild a game frame for frame. Settings only go in when they change
+/// something, since the UI sets them every frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum Input {
+  SetCapturing(bool),
+  SetTopology(Topology),
+  SetGrid(Grid),
+  SetSharing(Sharing),
+  SetSchedule(Schedule),
+  SetFlowWeight { player: usize, weight: f32 },
+  SetWall { pos: Point, wall: bool },
+  Place { player: usize, cells: Vec<Point> },
+  ToggleDead(Point),
+  SetSpeed { player: usize, speed: f32 },
+  SetKomi { player: usize, komi: f32 },
+  SetInk { player: usize, ink: Option<Ink> },
+  Spill { spigots: Vec<Vec<Option<Point>>>, count: u32, first: usize },
+}
+
+/// A log of every input a `Board` has received since it was made. Every board
+/// keeps one, see `Board::replay`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+  width: usize,
+  height: usize,
+  capturing: bool,
+  players: usize,
+  inputs: Vec<Input>,
+}
+
+impl Replay {
+  pub(crate) fn new(width: usize, height: usize, capturing: bool, players: usize) -> Self {
+    Replay { width, height, capturing, players, inputs: vec![] }
+  }
+
+  pub(crate) fn push(&mut self, input: Input) {
+    self.inputs.push(input);
+  }
+
+  /// The index in `inputs` of the `frame`th spill, or the end if there isn't one.
+  fn start_of(&self, frame: usize) -> usize {
+    self
+      .inputs
+      .iter()
+      .enumerate()
+      .filter(|(_, input)| matches!(input, Input::Spill { .. }))
+      .nth(frame)
+      .map_or(self.inputs.len(), |(idx, _)| idx)
+  }
+
+  /// Everything that happened before frame `frame`.
+  pub(crate) fn truncated(&self, frame: usize) -> Replay {
+    Replay { inputs: self.inputs[..self.start_of(frame)].to_vec(), ..*self }
+  }
+
+  /// Feed `board`, which must be at the start of frame `from`, the inputs up to
+  /// the start of frame `to`.
+  pub(crate) fn apply(&self, board: &mut Board, from: usize, to: usize) {
+    self.feed(board, self.start_of(from), from, to);
+  }
+
+  /// `apply`, starting from `inputs[start]`.
+  fn feed(&self, board: &mut Board, start: usize, from: usize, to: usize) {
+    let mut played = from;
+    for input in &self.inputs[start..] {
+      match *input {
+        Input::SetCapturing(capturing) => board.set_capturing(capturing),
+        Input::SetTopology(topology) => {
+          board.set_topology(topology);
+        }
+        Input::SetGrid(grid) => {
+          board.set_grid(grid);
+        }
+        Input::SetSharing(sharing) => board.set_sharing(sharing),
+        Input::SetSchedule(schedule) => board.set_schedule(schedule),
+        Input::SetFlowWeight { player, weight } => {
+          board.set_flow_weight(player, weight);
+        }
+        Input::SetWall { pos, wall } => {
+          board.set_wall(&pos, wall);
+        }
+        Input::Place { player, ref cells } => {
+          board.place(player, cells);
+        }
+        Input::ToggleDead(pos) => {
+          board.toggle_dead(&pos);
+        }
+        Input::SetSpeed { player, speed } => {
+          board.set_player_speed(player, speed);
+        }
+        Input::SetKomi { player, komi } => board.set_komi(player, komi),
+        Input::SetInk { player, ink } => {
+          board.replay.push(Input::SetInk { player, ink });
+          board.players[player].ink = ink;
+        }
+        Input::Spill { ref spigots, count, first } => {
+          if played == to {
+            break;
+          }
+          board.spill_spigots(spigots, count, first);
+          played += 1;
+        }
+      }
+    }
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Replay {
+  /// Encode the replay for sending along with a bug report.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    bincode::serialize(self).expect("a Replay is always serializable")
+  }
+
+  /// Returns None if `bytes` isn't a replay made by `to_bytes`.
+  pub fn from_bytes(bytes: &[u8]) -> Option<Replay> {
+    bincode::deserialize(bytes).ok()
+  }
+
+  /// The number of calls to `spill`, ie. the number of frames in the game.
+  pub fn frames(&self) -> usize {
+    self.inputs.iter().filter(|input| matches!(input, Input::Spill { .. })).count()
+  }
+
+  /// Re-simulate the whole game.
+  pub fn play(&self) -> Board {
+    self.play_to(usize::MAX)
+  }
+
+  /// Re-simulate the first `frames` frames of the game, along with any settings
+  /// changes made before the next one.
+  pub fn play_to(&self, frames: usize) -> Board {
+    let mut board = Board::with_players(self.width, self.height, self.capturing, self.players);
+    // A new board is from before any settings, not the start of the first frame.
+    self.feed(&mut board, 0, 0, frames);
+    board
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn settings_that_dont_change_arent_recorded() {
+    let mut board = Board::new(8, true);
+    for _ in 0..3 {
+      board.set_capturing(false);
+      board.set_sharing(Sharing::Each);
+      board.set_speed(true, 0.25);
+      board.set_komi(1, 0.5);
+    }
+    assert_eq!(board.replay().inputs.len(), 4);
+  }
+}
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+use std::mem;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Replay};
+
+/// A ring buffer of recent board states, for rollback netcode. Rather than undo
+/// every `assimilate` and `check_dead` (a UnionFind can't be split back apart),
+/// we keep a full copy every `interval` frames, and to rewind we restore the
+/// newest copy that's old enough and re-simulate the rest from the `Replay`.
+#[derive(Clone, Default)]
+pub(crate) struct Rollback {
+  interval: usize,
+  capacity: usize,
+  /// (frame, board at the start of that frame), oldest first. The boards don't
+  /// carry their history, see `Board::fork`.
+  snapshots: VecDeque<(usize, Board)>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Start keeping a copy of the board every `interval` frames, up to `capacity`
+  /// of them, so that we can `rewind` roughly `interval * capacity` frames. A
+  /// capacity of 0 turns rollback off. Copies are as big as the board, so a small
+  /// interval is fast to rewind but costs more memory and time per frame.
+  pub fn enable_rollback(&mut self, interval: usize, capacity: usize) {
+    self.rollback = Rollback { interval: interval.max(1), capacity, snapshots: VecDeque::new() };
+  }
+
+  /// Put the board back to how it was at the start of frame `frame`, as if the
+  /// later calls to `spill` never happened. Call `spill` again with corrected
+  /// inputs to fast-forward. Returns false and does nothing if `frame` is in the
+  /// future or older than our oldest copy.
+  pub fn rewind(&mut self, frame: usize) -> bool {
+    if frame > self.frame() {
+      return false;
+    }
+    while self.rollback.snapshots.back().is_some_and(|&(start, _)| start > frame) {
+      self.rollback.snapshots.pop_back();
+    }
+    let (start, mut board) = match self.rollback.snapshots.back() {
+      Some((start, board)) => (*start, board.clone()),
+      None => return false,
+    };
+
+    board.replay = self.replay.truncated(start);
+    board.checksums = self.checksums[..start].to_vec();
+    board.alt_image = mem::take(&mut self.alt_image);
+    board.heatmap = mem::take(&mut self.heatmap);
+    board.rollback = mem::take(&mut self.rollback);
+    let replay = mem::replace(&mut self.replay, Replay::new(0, 0, false, 0));
+    *self = board;
+    replay.apply(self, start, frame);
+    true
+  }
+}
+
+impl Board {
+  /// Called by `spill` at the start of every frame.
+  pub(crate) fn save_rollback(&mut self) {
+    let frame = self.frame();
+    let rollback = &self.rollback;
+    if rollback.capacity == 0
+      || !frame.is_multiple_of(rollback.interval)
+      || rollback.snapshots.back().is_some_and(|&(start, _)| start >= frame)
+    {
+      return;
+    }
+    let snapshot = self.fork();
+    let rollback = &mut self.rollback;
+    if rollback.snapshots.len() == rollback.capacity {
+      rollback.snapshots.pop_front();
+    }
+    rollback.snapshots.push_back((frame, snapshot));
+  }
+}
+
+impl Board {
+  /// A copy of the board that plays on exactly like this one, for looking ahead.
+  /// It leaves behind the things that only grow or that only matter for drawing:
+  /// the history (so the copy's `frame` starts over at 0 and its `replay` is
+  /// empty), the rollback copies and the screenbuffers, which `score` and
+  /// `estimate` fill in again if they're called. What's left is the cells and
+  /// the bodies with their liberty RTrees. Dead bodies are emptied when they die
+  /// and merged ones keep no data, so the union-find costs a few words per body
+  /// ever made on top of the live ones.
+  ///
+  /// Without the history, a fork's `replay` can't be played: it only has what
+  /// happened after the fork, and `Replay::play` would start that from an empty
+  /// board. Use `save` to keep a position that can be picked up again.
+  pub fn fork(&self) -> Board {
+    Board {
+      players: self.players.clone(),
+      capturing: self.capturing,
+      width: self.width,
+      height: self.height,
+      topology: self.topology,
+      grid: self.grid,
+      sharing: self.sharing,
+      schedule: self.schedule,
+      teams: self.teams.clone(),
+      image: self.image.clone(),
+      owner: self.owner.clone(),
+      bodies: self.bodies.clone(),
+      alt_image: vec![],
+      heatmap: vec![],
+      replay: Replay::new(self.width, self.height, self.capturing, self.players.len()),
+      teams_hash: self.teams_hash,
+      checksums: vec![],
+      rollback: Rollback::default(),
+    }
+  }
+}
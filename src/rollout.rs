@@ -0,0 +1,128 @@
+use super::{Aim, Board, Cutter, Defender, Expander, Point, Strategy, View};
+
+/// Looks ahead instead of following a rule of thumb. Every few frames it gathers
+/// some things it could do (keep going, pass, go where the other bots would go,
+/// or try a few random empty cells), plays each one out on a `Board::fork` for
+/// `horizon` frames, and keeps whichever leaves it furthest ahead by
+/// `Board::estimate`. In the playouts everyone else plays like an `Expander`.
+/// Thinking often matters more than thinking far: most games are lost by filling
+/// in your own last liberty, which only takes a few frames.
+#[derive(Clone, Debug)]
+pub struct Rollout {
+  /// Frames to play out for each candidate.
+  pub horizon: usize,
+  /// The `count` to pass `spill` in the playouts, which should match the game.
+  pub flow: u32,
+  /// Random empty cells to try, on top of the other bots' picks.
+  pub samples: usize,
+  /// Frames between rethinks. Thinking costs `(samples + 5) * horizon` frames of
+  /// simulation, so this trades strength for speed.
+  pub think_every: usize,
+  target: Option<Point>,
+  since: Option<usize>,
+  rng: u64,
+  expander: Expander,
+  cutter: Cutter,
+  defender: Defender,
+}
+
+impl Default for Rollout {
+  fn default() -> Self {
+    Rollout {
+      horizon: 30,
+      flow: 4,
+      samples: 4,
+      think_every: 5,
+      target: None,
+      since: None,
+      rng: 0x_9e37_79b9_7f4a_7c15,
+      expander: Expander::default(),
+      cutter: Cutter::default(),
+      defender: Defender::default(),
+    }
+  }
+}
+
+impl Rollout {
+  /// A different `seed` tries different random cells.
+  pub fn with_seed(seed: u64) -> Self {
+    Rollout { rng: seed | 1, ..Default::default() }
+  }
+
+  /// xorshift64, so bots play the same everywhere.
+  fn random(&mut self, below: usize) -> usize {
+    self.rng ^= self.rng << 13;
+    self.rng ^= self.rng >> 7;
+    self.rng ^= self.rng << 17;
+    (self.rng % below.max(1) as u64) as usize
+  }
+
+  /// Places to try, or None for passing, which matters once spilling more would
+  /// only fill in our own eyes.
+  fn candidates(&mut self, view: &View, player: usize) -> Vec<Option<Point>> {
+    let mut candidates: Vec<Option<Point>> = vec![
+      self.target,
+      None,
+      self.expander.aim(view, player).spigot(),
+      self.cutter.aim(view, player).spigot(),
+      self.defender.aim(view, player).spigot(),
+    ];
+    let wanted = candidates.len() + self.samples;
+    for _ in 0..self.samples * 4 {
+      if candidates.len() >= wanted {
+        break;
+      }
+      let pos = Point::new(self.random(view.width()) as isize, self.random(view.height()) as isize);
+      if view.is_empty(&pos) {
+        candidates.push(Some(pos));
+      }
+    }
+    let mut unique = vec![];
+    for candidate in candidates {
+      if !unique.contains(&candidate) {
+        unique.push(candidate);
+      }
+    }
+    unique
+  }
+
+  /// How far ahead of the best other player `player` is after spilling at
+  /// `target`, or passing, for `horizon` frames.
+  fn play_out(&self, board: &Board, player: usize, target: Option<Point>) -> f32 {
+    // Forks count frames from 0, but who goes first follows the real game.
+    let start = board.frame();
+    let mut board = board.fork();
+    let mut others: Vec<Expander> = vec![Expander::default(); board.players()];
+    for frame in 0..self.horizon {
+      let view = View::new(&board);
+      let spigots: Vec<Option<Point>> = (0..board.players())
+        .map(|p| if p == player { target } else { others[p].aim(&view, p).spigot() })
+        .collect();
+      board.spill_players(&spigots, self.flow, (start + frame) % spigots.len().max(1));
+    }
+    let estimate = board.estimate(u32::MAX, 1);
+    let best_other = (0..board.players())
+      .filter(|&p| p != player)
+      .map(|p| estimate.projected(p))
+      .fold(f32::MIN, f32::max);
+    estimate.projected(player) - best_other
+  }
+}
+
+impl Strategy for Rollout {
+  fn aim(&mut self, view: &View, player: usize) -> Aim {
+    let due = self.since.is_none_or(|since| view.frame() >= since + self.think_every);
+    if due || self.target.is_some_and(|target| view.player_at(&target).is_some_and(|p| p != player))
+    {
+      let candidates = self.candidates(view, player);
+      let scores: Vec<f32> =
+        candidates.iter().map(|&target| self.play_out(view.board(), player, target)).collect();
+      // Ties go to the earliest candidate, which is the one we already had.
+      let best =
+        (0..scores.len()).fold(0, |best, i| if scores[i] > scores[best] { i } else { best });
+      self.target = candidates[best];
+      self.since = Some(view.frame());
+    }
+    self.target.map_or(Aim::off(), Aim::at)
+  }
+}
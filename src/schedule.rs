@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use union_find::UnionFind;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Fixed, Input, Point, Team};
+
+/// Who spills when. Every frame is `count / 2` steps, and in each step every
+/// player gets about two turns, one cell per turn (or one per spigot, see
+/// `Sharing`). Where two players' liquid meets, whoever goes first takes the
+/// cell, so this decides who wins a contested front. Every schedule starts from
+/// the `first` player passed to `spill`, which callers should move on every frame.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+  /// Every step goes first, second, ..., last, then back from last to first. With
+  /// two players that's ABBA, so nobody gets two turns in a row for free. This is
+  /// the default.
+  Snake,
+  /// Every step goes first, second, ..., last, twice over, ie. ABAB.
+  Alternate,
+  /// Every player picks their cell at the same time, twice a step. If two players
+  /// pick the same cell, the one with priority gets it and the others lose their
+  /// turn. Priority goes round the players, one place every turn. Captures still
+  /// happen one move at a time, in order of priority.
+  Simultaneous,
+  /// Like `Snake`, but each player gets as many turns a step as their weight
+  /// times two, see `set_flow_weight`. Fractions carry over to the next step.
+  Weighted,
+}
+
+impl Schedule {
+  /// The turns in one step, as rounds. Each round lists players in order of
+  /// priority, and only `Simultaneous` has more than one player per round.
+  pub(crate) fn rounds(
+    self,
+    step: usize,
+    first: usize,
+    weights: &[Fixed],
+    credits: &mut [Fixed],
+  ) -> Vec<Vec<usize>> {
+    let n = weights.len().max(1);
+    let from = |start: usize| (0..n).map(move |i| (start + i) % n);
+    let snake = || from(first).chain(from(first).collect::<Vec<_>>().into_iter().rev());
+    match self {
+      Schedule::Snake => snake().map(|player| vec![player]).collect(),
+      Schedule::Alternate => from(first).chain(from(first)).map(|player| vec![player]).collect(),
+      Schedule::Simultaneous => {
+        vec![from(first + 2 * step).collect(), from(first + 2 * step + 1).collect()]
+      }
+      Schedule::Weighted => {
+        for (credit, &weight) in credits.iter_mut().zip(weights) {
+          *credit = *credit + weight + weight;
+        }
+        // Snake through everyone with a whole turn of credit left until nobody has.
+        let mut rounds = vec![];
+        let mut forwards = true;
+        let turn = Fixed::from_int(1);
+        while credits.iter().any(|&credit| credit >= turn) {
+          let mut order: Vec<usize> = from(first).collect();
+          if !forwards {
+            order.reverse();
+          }
+          for player in order {
+            if credits[player] >= turn {
+              credits[player] = credits[player] - turn;
+              rounds.push(vec![player]);
+            }
+          }
+          forwards = !forwards;
+        }
+        rounds
+      }
+    }
+  }
+}
+
+/// The most turns a player can get for each of an unweighted player's, which is
+/// plenty for a handicap and keeps a step from going on forever.
+pub(crate) const MAX_WEIGHT: f32 = 16.;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Part of the rules, so set it before the first `spill` and keep it for the
+  /// whole game.
+  pub fn set_schedule(&mut self, schedule: Schedule) {
+    if schedule != self.schedule {
+      self.replay.push(Input::SetSchedule(schedule));
+      self.schedule = schedule;
+    }
+  }
+
+  /// How many turns `player` gets under `Schedule::Weighted`, relative to the
+  /// default of 1. A weight of 1.5 spills half again as fast, for a handicap. Other
+  /// schedules ignore this. Weights are kept between 0 and `MAX_WEIGHT`, and this
+  /// returns false and changes nothing if `weight` isn't a number.
+  pub fn set_flow_weight(&mut self, player: usize, weight: f32) -> bool {
+    if !weight.is_finite() {
+      return false;
+    }
+    let fixed = Fixed::from_f32(weight.clamp(0., MAX_WEIGHT));
+    if fixed != self.players[player].weight {
+      self.replay.push(Input::SetFlowWeight { player, weight });
+      self.players[player].weight = fixed;
+    }
+    true
+  }
+}
+
+impl Board {
+  /// Take a `Schedule::Simultaneous` round. `moves` are the (player, spigot) pairs
+  /// whose turn it is, in order of priority. Returns the ones that are done for
+  /// the frame, like `assimilate`.
+  pub(crate) fn clash(
+    &mut self,
+    moves: &[(usize, usize)],
+    keys: &[Vec<Option<usize>>],
+  ) -> Vec<(usize, usize)> {
+    // Everyone picks first, then the picks are carried out.
+    let (topology, width, height) = (self.topology, self.width, self.height);
+    let mut picks: Vec<(usize, usize, Option<Point>)> = vec![];
+    for &(player, i) in moves {
+      let (Some(pos), Some(key)) = (self.players[player].spigots[i].pos, keys[player][i]) else {
+        continue;
+      };
+      let pick =
+        topology.nearest_lib(&self.bodies.get(key).libs, Point::from_fixed(pos), width, height);
+      if pick.is_none() || picks.iter().all(|&(_, _, other)| other != pick) {
+        picks.push((player, i, pick));
+      }
+    }
+
+    let mut done = vec![];
+    for (player, i, pick) in picks {
+      let (key, us) = (keys[player][i].expect("only spigots with keys pick"), Team(player as u8));
+      if self.dry(player) {
+        continue;
+      }
+      let finished = match pick {
+        // An earlier pick might have captured this body already.
+        Some(pos) => {
+          self.bodies.get_mut(key).libs.remove(&pos).is_some() && self.absorb(pos, key, us)
+        }
+        None => self.check_dead(key, us),
+      };
+      if finished {
+        done.push((player, i));
+      }
+    }
+    done
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn flow_weights_stay_finite() {
+    let mut board = Board::new(16, true);
+    board.set_schedule(Schedule::Weighted);
+    assert!(!board.set_flow_weight(1, f32::INFINITY));
+    assert!(!board.set_flow_weight(1, f32::NAN));
+    assert!(board.set_flow_weight(1, 1e30));
+    let spigots = [Some(Point::new(4, 8)), Some(Point::new(12, 8))];
+    board.spill_players(&spigots, 4, 0);
+    assert_eq!(board.score().stone(1), (4. * MAX_WEIGHT) as isize);
+  }
+}
@@ -1,14 +1,27 @@
 use union_find::{QuickFindUf as UF, Union, UnionFind, UnionResult};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use super::{
-  console::debug,
-  Board,
-  Team::{self, *},
-};
+use super::{Board, Input, Point, Team, EMPTY_COLOR, MAX_PLAYERS, WALL_COLOR};
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Board {
+  /// Give `player` `komi` extra points at the end of the game, to make up for
+  /// moving later. In two player games it's usually White (player 1) who gets it.
+  /// A fraction of a cell rules out ties.
+  pub fn set_komi(&mut self, player: usize, komi: f32) {
+    if komi != self.players[player].komi {
+      self.replay.push(Input::SetKomi { player, komi });
+      self.players[player].komi = komi;
+    }
+  }
+
+  /// `set_komi` as a fraction of the area of the board, so that the same setting
+  /// is fair on every size of board.
+  pub fn set_komi_fraction(&mut self, player: usize, fraction: f32) {
+    self.set_komi(player, fraction * self.area() as f32);
+  }
+
   pub fn score(&mut self) -> ScoreResult {
     // We are again pulling the first layer out of the UnionFind,
     // only because UnionFind doesn't provide a reasonable way to
@@ -16,94 +29,234 @@ impl Board {
     // solved by default public members.
     let mut owners = Vec::with_capacity(self.area());
     let mut territory = UF::from_iter([]);
+    // Pairs of neighboring cells in different regions, not counting walls.
+    let mut borders = Vec::new();
+
+    // Bodies marked dead are counted as the empty board they'll become, so they
+    // end up in the territory around them and are lost by their player.
+    let mut teams = self.teams.clone();
+    let mut dead = vec![0; self.players.len()];
+    for (team, &owner) in teams.iter_mut().zip(&self.owner) {
+      if let Some(player) = team.player() {
+        if self.bodies.get(owner).marked {
+          *team = Team::EMPTY;
+          dead[player] += 1;
+        }
+      }
+    }
 
     for idx in 0..self.area() {
-      let neighbors = [
-        if idx % self.size != 0 { Some(idx - 1) } else { None },
-        if idx >= self.size { Some(idx - self.size) } else { None },
-      ]
-      .into_iter()
-      .flatten();
-
-      let mut friends = neighbors.clone().filter(|other| self.teams[idx] == self.teams[*other]);
+      // Only the neighbors we've already been to, so every pair is looked at once.
+      // That includes pairs across the seams of a torus, from their far side.
+      let pos = Point::new((idx % self.width) as isize, (idx / self.width) as isize);
+      let neighbors = self.neighbors(pos).map(|p| self.get_idx(p)).filter(|&other| other < idx);
+
+      let mut friends = neighbors.clone().filter(|other| teams[idx] == teams[*other]);
       if let Some(besty) = friends.next() {
         owners.push(owners[besty]);
-        if let Some(buddy) = friends.next() {
+        for buddy in friends {
           territory.union(owners[idx], owners[buddy]);
         }
       } else {
-        owners.push(territory.insert(Body::new(self.teams[idx])));
+        owners.push(territory.insert(Body::new(teams[idx])));
       }
 
       let bod = territory.get_mut(owners[idx]);
       bod.count += 1;
-      neighbors.clone().for_each(|neigh| match self.teams[neigh] {
-        Black => bod.touch_black = true,
-        White => bod.touch_white = true,
-        Empty => (),
-      });
-      match self.teams[idx] {
-        Black => neighbors.for_each(|neigh| territory.get_mut(owners[neigh]).touch_black = true),
-        White => neighbors.for_each(|neigh| territory.get_mut(owners[neigh]).touch_white = true),
-        Empty => (),
+      neighbors.clone().for_each(|neigh| bod.touching |= teams[neigh].bit());
+      let bit = teams[idx].bit();
+      neighbors.clone().for_each(|neigh| territory.get_mut(owners[neigh]).touching |= bit);
+      let walls = |team: Team| team == Team::WALL;
+      borders.extend(
+        neighbors
+          .filter(|&neigh| teams[neigh] != teams[idx] && !walls(teams[neigh]) && !walls(teams[idx]))
+          .map(|neigh| (idx, neigh)),
+      );
+    }
+
+    // Empty cells that aren't next to any liquid. A region with one of those has
+    // room for whoever gets there first to make an eye in it, like the open board
+    // at the start of the game, so it can't be seki.
+    for idx in 0..self.area() {
+      let pos = Point::new((idx % self.width) as isize, (idx / self.width) as isize);
+      if teams[idx] == Team::EMPTY
+        && self.neighbors(pos).all(|neigh| teams[self.get_idx(neigh)].player().is_none())
+      {
+        territory.get_mut(owners[idx]).inland = true;
       }
     }
 
-    let mut b_stones = 0;
-    let mut w_stones = 0;
-    let mut b_territory = 0;
-    let mut w_territory = 0;
-
-    for key in 0..territory.size() {
-      if territory.find(key) == key {
-        let bod = territory.get(key);
-        match bod.team {
-          Black => b_stones += bod.count,
-          White => w_stones += bod.count,
-          Empty => match (bod.touch_black, bod.touch_white) {
-            (true, false) => b_territory += bod.count,
-            (false, true) => w_territory += bod.count,
-            _ => (),
-          },
+    // Liquid next to its own territory has an eye. A neutral region that's nothing
+    // but liberties, where none of the liquid around it has an eye, is what's
+    // keeping all of it alive, so it's seki. If any of it has an eye, filling the
+    // region in just gets the eyeless liquid captured, so it's dame.
+    let mut shores: Vec<(usize, usize)> = borders
+      .iter()
+      .filter_map(|&(a, b)| {
+        let (a, b) = (territory.find(owners[a]), territory.find(owners[b]));
+        match (territory.get(a).team, territory.get(b).team) {
+          (Team::EMPTY, _) => Some((b, a)),
+          (_, Team::EMPTY) => Some((a, b)),
+          _ => None,
         }
+      })
+      .collect();
+    shores.sort_unstable();
+    shores.dedup();
+    for &(liquid, empty) in &shores {
+      let player = territory.get(liquid).team.player();
+      if territory.get(empty).owner().is_some_and(|owner| Some(owner) == player) {
+        territory.get_mut(liquid).eye = true;
+      }
+    }
+    for &(_, empty) in &shores {
+      let region = territory.get(empty);
+      if region.neutral() && !region.inland {
+        territory.get_mut(empty).seki = true;
+      }
+    }
+    for &(liquid, empty) in &shores {
+      if territory.get(liquid).eye {
+        territory.get_mut(empty).seki = false;
       }
     }
 
-    for idx in 0..self.area() {
-      let bod = territory.get(owners[idx]);
-      self.alt_image[idx] = 1;
-      self.alt_image[idx] = match bod.team {
-        Black => color(Black),
-        White => color(White),
-        Empty => match (bod.touch_black, bod.touch_white) {
-          (true, false) => color_average(Black, Empty),
-          (false, true) => color_average(White, Empty),
-          _ => color(Empty),
-        },
+    let mut stones = vec![0; self.players.len()];
+    let mut territories = vec![0; self.players.len()];
+    let (mut dame, mut seki) = (0, 0);
+
+    // One Region per set in the UnionFind, numbered in the order we first see them.
+    let mut numbers = vec![usize::MAX; territory.size()];
+    let mut regions: Vec<Region> = vec![];
+    let mut cells = Vec::with_capacity(self.area());
+    for (idx, &owner) in owners.iter().enumerate() {
+      let root = territory.find(owner);
+      let (x, y) = ((idx % self.width) as isize, (idx / self.width) as isize);
+      if numbers[root] == usize::MAX {
+        numbers[root] = regions.len();
+        let bod = territory.get(root);
+        regions.push(Region {
+          kind: bod.kind(),
+          player: bod.team.player().or(bod.owner()),
+          area: bod.count,
+          touching: bod.touching & !bod.team.bit(),
+          left: x,
+          top: y,
+          right: x,
+          bottom: y,
+        });
       }
+      let region = &mut regions[numbers[root]];
+      region.left = region.left.min(x);
+      region.top = region.top.min(y);
+      region.right = region.right.max(x);
+      region.bottom = region.bottom.max(y);
+      cells.push(numbers[root]);
     }
 
-    ScoreResult {
-      b_stone: b_stones,
-      w_stone: w_stones,
-      b_china: b_stones + b_territory,
-      w_china: w_stones + w_territory,
-      b_japan: b_territory - self.w_prisoners,
-      w_japan: w_territory - self.b_prisoners,
+    for region in &regions {
+      match (region.kind, region.player) {
+        (RegionKind::Liquid, Some(player)) => stones[player] += region.area,
+        (RegionKind::Territory, Some(player)) => territories[player] += region.area,
+        (RegionKind::Seki, _) => seki += region.area,
+        (RegionKind::Dame, _) => dame += region.area,
+        _ => (),
+      }
     }
+
+    self.alt_image.resize(cells.len(), 0);
+    for (idx, (pixel, &number)) in self.alt_image.iter_mut().zip(&cells).enumerate() {
+      let region = &regions[number];
+      *pixel = match (region.kind, region.player) {
+        (RegionKind::Liquid, Some(player)) => self.players[player].color,
+        (RegionKind::Territory, Some(player)) => {
+          color_average(self.players[player].color, EMPTY_COLOR)
+        }
+        (RegionKind::Seki, _) => SEKI_COLOR,
+        (RegionKind::Dame, _) => DAME_COLOR,
+        (RegionKind::Wall, _) => WALL_COLOR,
+        _ => EMPTY_COLOR,
+      };
+      // Dead liquid shows through faintly over whatever it counts as.
+      if teams[idx] != self.teams[idx] {
+        let player = self.teams[idx].player().expect("only liquid can be dead");
+        *pixel = color_average(self.players[player].color, *pixel);
+      }
+    }
+
+    let china =
+      stones.iter().zip(&territories).map(|(stones, territory)| stones + territory).collect();
+    let japan = territories
+      .iter()
+      .zip(&self.players)
+      .zip(&dead)
+      .map(|((territory, player), dead)| territory - player.lost - dead)
+      .collect();
+    let komi = self.players.iter().map(|player| player.komi).collect();
+    let mut result = ScoreResult::new(stones, china, japan, komi);
+    result.dame = dame;
+    result.seki = seki;
+    result.regions = regions;
+    result.cells = cells;
+    result.width = self.width;
+    result
   }
 }
 
+/// Neutral empty board in `alt_image`, between living liquid of different players.
+const DAME_COLOR: u32 = 0x_ff_80_c0_d8;
+/// Empty board in seki in `alt_image`.
+const SEKI_COLOR: u32 = 0x_ff_a0_60_c0;
+
 struct Body {
   count: isize,
   team: Team,
-  touch_black: bool,
-  touch_white: bool,
+  /// Bit i is set if this region touches player i.
+  touching: u64,
+  /// Liquid that touches its own territory.
+  eye: bool,
+  /// Empty board with a cell that isn't next to any liquid.
+  inland: bool,
+  /// Neutral empty board that's all liberties, where none of the liquid around it
+  /// has an eye.
+  seki: bool,
 }
 
 impl Body {
   fn new(team: Team) -> Self {
-    Body { count: 0, team, touch_black: false, touch_white: false }
+    Body { count: 0, team, touching: 0, eye: false, inland: false, seki: false }
+  }
+
+  /// Empty board touching more than one player, which is nobody's territory.
+  fn neutral(&self) -> bool {
+    self.team == Team::EMPTY && self.touching.count_ones() > 1
+  }
+
+  fn kind(&self) -> RegionKind {
+    match (self.team, self.owner()) {
+      (Team::WALL, _) => RegionKind::Wall,
+      (Team::EMPTY, Some(_)) => RegionKind::Territory,
+      (Team::EMPTY, None) if self.seki => RegionKind::Seki,
+      (Team::EMPTY, None) if self.neutral() => RegionKind::Dame,
+      (Team::EMPTY, None) => RegionKind::Unclaimed,
+      _ => RegionKind::Liquid,
+    }
+  }
+
+  /// The player whose territory this is, if it's empty board touching only them.
+  fn owner(&self) -> Option<usize> {
+    if self.team == Team::EMPTY && self.touching.count_ones() == 1 {
+      Some(self.touching.trailing_zeros() as usize)
+    } else {
+      None
+    }
+  }
+}
+
+impl Team {
+  /// This team's bit in `Body::touching`.
+  fn bit(self) -> u64 {
+    self.player().map_or(0, |player| 1 << player)
   }
 }
 
@@ -114,8 +267,10 @@ impl Union for Body {
     let result = Body {
       count: lval.count + rval.count,
       team: lval.team,
-      touch_black: lval.touch_black || rval.touch_black,
-      touch_white: lval.touch_white || rval.touch_white,
+      touching: lval.touching | rval.touching,
+      eye: lval.eye || rval.eye,
+      inland: lval.inland || rval.inland,
+      seki: lval.seki || rval.seki,
     };
 
     if lval.count > rval.count {
@@ -126,7 +281,10 @@ impl Union for Body {
   }
 }
 
-#[wasm_bindgen]
+/// The b_ and w_ fields are players 0 and 1, for two player games. Use the
+/// methods for any player. The counts are raw, `total` and `winner` add komi.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug)]
 pub struct ScoreResult {
   pub b_stone: isize,
   pub w_stone: isize,
@@ -134,19 +292,310 @@ pub struct ScoreResult {
   pub w_china: isize,
   pub b_japan: isize,
   pub w_japan: isize,
+  /// Cells of neutral empty board between living liquid of different players.
+  pub dame: isize,
+  /// Cells of neutral empty board that liquid without an eye lives on, where every
+  /// cell is a liberty. Like dame, nobody scores these.
+  pub seki: isize,
+  stone: Vec<isize>,
+  china: Vec<isize>,
+  japan: Vec<isize>,
+  komi: Vec<f32>,
+  regions: Vec<Region>,
+  /// cells[i] is the index in `regions` of cell i.
+  cells: Vec<usize>,
+  width: usize,
+}
+
+/// What a `Region` counts as.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+  /// A player's living liquid.
+  Liquid,
+  /// Empty board touching only one player's liquid.
+  Territory,
+  /// Empty board between living liquid of different players.
+  Dame,
+  /// Empty board that liquid without an eye lives on, all of it liberties.
+  Seki,
+  /// Empty board that doesn't touch any liquid.
+  Unclaimed,
+  Wall,
+}
+
+/// A connected piece of the board that's all the same thing, as counted by `score`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct Region {
+  kind: RegionKind,
+  player: Option<usize>,
+  area: isize,
+  touching: u64,
+  left: isize,
+  top: isize,
+  right: isize,
+  bottom: isize,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Region {
+  pub fn kind(&self) -> RegionKind {
+    self.kind
+  }
+
+  /// Whose liquid or territory this is. None for everything else.
+  pub fn player(&self) -> Option<usize> {
+    self.player
+  }
+
+  /// The number of cells in the region.
+  pub fn area(&self) -> isize {
+    self.area
+  }
+
+  /// Whether any of `player`'s liquid is next to this region, not counting the
+  /// region itself.
+  pub fn touches(&self, player: usize) -> bool {
+    player < MAX_PLAYERS && self.touching & 1 << player != 0
+  }
+
+  /// The bounding box, inclusive. On a torus, a region that goes across an edge
+  /// spans the whole board that way.
+  pub fn left(&self) -> isize {
+    self.left
+  }
+
+  pub fn top(&self) -> isize {
+    self.top
+  }
+
+  pub fn right(&self) -> isize {
+    self.right
+  }
+
+  pub fn bottom(&self) -> isize {
+    self.bottom
+  }
+}
+
+/// The ways to count a finished game.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scoring {
+  /// Liquid on the board.
+  Stone,
+  /// Area: liquid plus territory.
+  China,
+  /// Territory minus the cells lost to captures.
+  Japan,
+}
+
+impl ScoreResult {
+  fn new(stone: Vec<isize>, china: Vec<isize>, japan: Vec<isize>, komi: Vec<f32>) -> Self {
+    let get = |scores: &Vec<isize>, player: usize| scores.get(player).copied().unwrap_or(0);
+    ScoreResult {
+      b_stone: get(&stone, 0),
+      w_stone: get(&stone, 1),
+      b_china: get(&china, 0),
+      w_china: get(&china, 1),
+      b_japan: get(&japan, 0),
+      w_japan: get(&japan, 1),
+      dame: 0,
+      seki: 0,
+      regions: vec![],
+      cells: vec![],
+      width: 0,
+      stone,
+      china,
+      japan,
+      komi,
+    }
+  }
+
+  /// Every region on the board.
+  pub fn regions(&self) -> &[Region] {
+    &self.regions
+  }
+
+  fn raw(&self, scoring: Scoring) -> &[isize] {
+    match scoring {
+      Scoring::Stone => &self.stone,
+      Scoring::China => &self.china,
+      Scoring::Japan => &self.japan,
+    }
+  }
+
+  /// Players from first to last place, ties broken by liquid on the board.
+  fn ranking(&self, scoring: Scoring) -> Vec<usize> {
+    let mut ranking: Vec<usize> = (0..self.stone.len()).collect();
+    ranking.sort_by(|&a, &b| {
+      let total = self.total(scoring, b).total_cmp(&self.total(scoring, a));
+      total.then(self.stone[b].cmp(&self.stone[a]))
+    });
+    ranking
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ScoreResult {
+  /// Cells of liquid `player` has on the board.
+  pub fn stone(&self, player: usize) -> isize {
+    self.stone[player]
+  }
+
+  /// Liquid plus territory.
+  pub fn china(&self, player: usize) -> isize {
+    self.china[player]
+  }
+
+  /// Territory minus the cells `player` has had captured.
+  pub fn japan(&self, player: usize) -> isize {
+    self.japan[player]
+  }
+
+  /// The komi `total` adds for `player`.
+  pub fn komi(&self, player: usize) -> f32 {
+    self.komi[player]
+  }
+
+  /// The number of regions. They're numbered from the top left, in the order of
+  /// their first cells.
+  pub fn region_count(&self) -> usize {
+    self.regions.len()
+  }
+
+  pub fn region(&self, number: usize) -> Region {
+    self.regions[number].clone()
+  }
+
+  /// The number of the region that `pos` is in.
+  pub fn region_at(&self, pos: &Point) -> usize {
+    self.cells[pos.x as usize + pos.y as usize * self.width]
+  }
+
+  /// `player`'s final score counted by `scoring`, with their komi.
+  pub fn total(&self, scoring: Scoring, player: usize) -> f32 {
+    self.raw(scoring)[player] as f32 + self.komi[player]
+  }
+
+  /// The player with the highest `total`. If that's a tie, whoever of them has
+  /// more liquid on the board wins, and if that's a tie too it's a draw (None).
+  pub fn winner(&self, scoring: Scoring) -> Option<usize> {
+    let ranking = self.ranking(scoring);
+    match ranking[..] {
+      [] => None,
+      [only] => Some(only),
+      [first, second, ..] => {
+        let tied = self.total(scoring, first) == self.total(scoring, second)
+          && self.stone[first] == self.stone[second];
+        if tied {
+          None
+        } else {
+          Some(first)
+        }
+      }
+    }
+  }
+
+  /// How far ahead of second place the `winner` is. 0 if it was decided by the
+  /// tie-break or is a draw.
+  pub fn margin(&self, scoring: Scoring) -> f32 {
+    let ranking = self.ranking(scoring);
+    match ranking[..] {
+      [first, second, ..] => self.total(scoring, first) - self.total(scoring, second),
+      _ => 0.,
+    }
+  }
 }
 
 #[allow(clippy::zero_prefixed_literal)]
 #[allow(clippy::precedence)]
-fn color_average(a: Team, b: Team) -> u32 {
-  let a = unsafe { std::mem::transmute::<Team, u32>(a) };
-  let b = unsafe { std::mem::transmute::<Team, u32>(b) };
+fn color_average(a: u32, b: u32) -> u32 {
   (a >> 24) + (b >> 24) >> 1 << 24
     | ((a >> 16) + (b >> 16) >> 1 & 0xff) << 16
     | ((a >> 08) + (b >> 08) >> 1 & 0xff) << 08
     | (a & 0xff) + (b & 0xff) >> 1 & 0xff
 }
 
-fn color(team: Team) -> u32 {
-  unsafe { std::mem::transmute::<Team, u32>(team) }
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// Black and White walls with territory behind them, and open board between.
+  fn walls() -> Board {
+    let mut board = Board::with_players(20, 10, true, 2);
+    board.place_rect(0, &Point::new(2, 0), 4, 10);
+    board.place_rect(1, &Point::new(14, 0), 4, 10);
+    board
+  }
+
+  #[test]
+  fn open_board_between_living_walls_is_dame() {
+    let score = walls().score();
+    assert_eq!((score.dame, score.seki), (80, 0));
+  }
+
+  #[test]
+  fn open_board_is_not_seki() {
+    let mut board = Board::with_players(20, 20, true, 2);
+    board.place_disc(0, &Point::new(5, 5), 2.);
+    board.place_disc(1, &Point::new(14, 14), 2.);
+    let score = board.score();
+    assert_eq!((score.dame, score.seki), (374, 0));
+  }
+
+  #[test]
+  fn shared_liberties_of_eyeless_liquid_are_seki() {
+    // Black on the left and White on the right fill the board, apart from a
+    // column of cells between them that neither can fill without being captured.
+    let mut board = Board::with_players(9, 5, true, 2);
+    board.place_rect(0, &Point::new(0, 0), 4, 5);
+    board.place_rect(1, &Point::new(5, 0), 4, 5);
+    let score = board.score();
+    assert_eq!((score.dame, score.seki), (0, 5));
+  }
+
+  #[test]
+  fn eyeless_liquid_next_to_eyes_is_not_seki() {
+    let mut board = walls();
+    board.place(1, &[Point::new(10, 5)]);
+    let score = board.score();
+    assert_eq!((score.dame, score.seki), (79, 0));
+  }
+
+  /// Black and White with `black` and `white` cells of liquid in the top row.
+  fn stones(black: isize, white: isize) -> Board {
+    let mut board = Board::new(12, true);
+    board.place_rect(0, &Point::new(0, 0), black as usize, 1);
+    board.place_rect(1, &Point::new(12 - white, 0), white as usize, 1);
+    board
+  }
+
+  #[test]
+  fn komi_decides_the_winner() {
+    let mut board = stones(5, 4);
+    board.set_komi(1, 1.5);
+    let score = board.score();
+    assert_eq!(score.winner(Scoring::Stone), Some(1));
+    assert_eq!(score.margin(Scoring::Stone), 0.5);
+  }
+
+  #[test]
+  fn ties_go_to_more_liquid() {
+    let mut board = stones(4, 3);
+    board.set_komi(1, 1.);
+    let score = board.score();
+    assert_eq!(score.winner(Scoring::Stone), Some(0));
+    assert_eq!(score.margin(Scoring::Stone), 0.);
+  }
+
+  #[test]
+  fn a_full_tie_is_a_draw() {
+    let score = stones(3, 3).score();
+    for scoring in [Scoring::Stone, Scoring::China, Scoring::Japan] {
+      assert_eq!(score.winner(scoring), None);
+      assert_eq!(score.margin(scoring), 0.);
+    }
+  }
 }
@@ -0,0 +1,79 @@
+use union_find::UnionFind;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Body, Input, Point, Team};
+
+/// Putting liquid straight on the board, for handicaps, puzzles and tests. Only
+/// empty cells are filled, and cells off the board are skipped (or wrap around on
+/// a torus). Nothing is captured by placing: a body left without liberties dies
+/// the next time something spills next to it. Each returns the cells filled.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Fill every cell within `radius` of `center`.
+  pub fn place_disc(&mut self, player: usize, center: &Point, radius: f32) -> usize {
+    let reach = radius.max(0.) as isize;
+    let cells: Vec<Point> = (-reach..=reach)
+      .flat_map(|dy| (-reach..=reach).map(move |dx| (dx, dy)))
+      .filter(|&(dx, dy)| ((dx * dx + dy * dy) as f32) <= radius * radius)
+      .map(|(dx, dy)| Point::new(center.x + dx, center.y + dy))
+      .collect();
+    self.place(player, &cells)
+  }
+
+  /// Fill the `width` by `height` rectangle with its top left at `corner`.
+  pub fn place_rect(
+    &mut self,
+    player: usize,
+    corner: &Point,
+    width: usize,
+    height: usize,
+  ) -> usize {
+    let cells: Vec<Point> = (0..height as isize)
+      .flat_map(|y| (0..width as isize).map(move |x| Point::new(corner.x + x, corner.y + y)))
+      .collect();
+    self.place(player, &cells)
+  }
+
+  /// Fill the cells where `mask` is nonzero. `mask` is rows of `width` cells, the
+  /// same layout as the image, with its top left at `corner`. A mask with no
+  /// width has no cells.
+  pub fn place_mask(&mut self, player: usize, corner: &Point, width: usize, mask: &[u8]) -> usize {
+    if width == 0 {
+      return 0;
+    }
+    let cells: Vec<Point> = mask
+      .iter()
+      .enumerate()
+      .filter(|&(_, &on)| on != 0)
+      .map(|(i, _)| Point::new(corner.x + (i % width) as isize, corner.y + (i / width) as isize))
+      .collect();
+    self.place(player, &cells)
+  }
+}
+
+impl Board {
+  /// Fill `cells` with `player`'s liquid, in order.
+  pub fn place(&mut self, player: usize, cells: &[Point]) -> usize {
+    assert!(player < self.players.len(), "there is no player {}", player);
+    self.replay.push(Input::Place { player, cells: cells.to_vec() });
+    let team = Team(player as u8);
+    let mut placed = 0;
+    for &pos in cells {
+      let pos = match self.topology.wrap(pos, self.width, self.height) {
+        Some(pos) if self.get_teams(pos) == Team::EMPTY => pos,
+        _ => continue,
+      };
+      // Joining a body we've already placed saves making one per cell, `fill`
+      // merges in any others.
+      let key = self
+        .neighbors(pos)
+        .find(|&neighbor| self.get_teams(neighbor) == team)
+        .map(|neighbor| self.get_owner(neighbor))
+        .unwrap_or_else(|| self.bodies.insert(Body::new(vec![], team)));
+      self.fill(pos, key, team);
+      placed += 1;
+    }
+    placed
+  }
+}
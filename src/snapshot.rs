@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use union_find::{QuickFindUf as UF, UnionFind};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{
+  checksum, schedule::MAX_WEIGHT, Board, Body, Fixed, Grid, Player, Point, Replay, Rollback,
+  Schedule, Sharing, Team, Topology, MAX_PLAYERS, MAX_SPEED,
+};
+
+/// Everything needed to rebuild a `Board` that keeps spilling exactly like the
+/// original. We can't just derive Serialize on `Board`, since `UnionFind` only
+/// lets us look inside with a `&mut`, and it doesn't derive it anyways.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+  players: Vec<Player>,
+  capturing: bool,
+  width: usize,
+  height: usize,
+  topology: Topology,
+  grid: Grid,
+  sharing: Sharing,
+  schedule: Schedule,
+  teams: Vec<Team>,
+  owner: Vec<usize>,
+  /// roots[key] is `bodies.find(key)`.
+  roots: Vec<usize>,
+  /// The Body of every key that is its own root, in order of key. The RTrees
+  /// are saved node for node, so that nearest neighbor ties are broken the same
+  /// way after loading.
+  bodies: Vec<Body>,
+  replay: Replay,
+  checksums: Vec<u32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Encode the whole board, for saving a game or shipping a puzzle position.
+  /// This takes `&mut self` only because reading a `UnionFind` does. Rollback
+  /// copies are not saved.
+  pub fn save(&mut self) -> Vec<u8> {
+    let roots: Vec<usize> = (0..self.bodies.size()).map(|key| self.bodies.find(key)).collect();
+    let bodies = (0..roots.len())
+      .filter(|&key| roots[key] == key)
+      .map(|key| self.bodies.get(key).clone())
+      .collect();
+    let snapshot = Snapshot {
+      players: self.players.clone(),
+      capturing: self.capturing,
+      width: self.width,
+      height: self.height,
+      topology: self.topology,
+      grid: self.grid,
+      sharing: self.sharing,
+      schedule: self.schedule,
+      teams: self.teams.clone(),
+      owner: self.owner.clone(),
+      roots,
+      bodies,
+      replay: self.replay.clone(),
+      checksums: self.checksums.clone(),
+    };
+    bincode::serialize(&snapshot).expect("a Snapshot is always serializable")
+  }
+
+  /// Returns None if `bytes` isn't a board made by `save`.
+  pub fn load(bytes: &[u8]) -> Option<Board> {
+    let snapshot: Snapshot = bincode::deserialize(bytes).ok()?;
+    let area = snapshot.width.checked_mul(snapshot.height)?;
+    let keys = snapshot.roots.len();
+    let root_count = (0..keys).filter(|&key| snapshot.roots[key] == key).count();
+    if snapshot.players.is_empty()
+      || snapshot.players.len() > MAX_PLAYERS
+      || !snapshot.grid.fits(snapshot.topology, snapshot.height)
+      || snapshot.teams.len() != area
+      || snapshot
+        .teams
+        .iter()
+        .any(|team| team.player().is_some_and(|p| p >= snapshot.players.len()))
+      || snapshot.owner.len() != area
+      || snapshot.owner.iter().any(|&key| key >= keys)
+      || snapshot.roots.iter().any(|&root| root >= keys || snapshot.roots[root] != root)
+      || snapshot.bodies.len() != root_count
+    {
+      return None;
+    }
+    // The bodies' points and keys get used as indices without checking later.
+    let on_board = |pos: &Point| {
+      (0..snapshot.width as isize).contains(&pos.x)
+        && (0..snapshot.height as isize).contains(&pos.y)
+    };
+    let sound = |body: &Body| {
+      body.libs.iter().all(on_board)
+        && body.stollen_libs.iter().all(|(pos, key)| on_board(pos) && *key < keys)
+    };
+    if !snapshot.bodies.iter().all(sound) {
+      return None;
+    }
+    // So are the spigots, which only stay on the board at speeds `set_player_speed`
+    // allows. Weights past `set_flow_weight`'s could overflow the turn credits.
+    let in_range = |x: Fixed, max: f32| (Fixed::default()..=Fixed::from_f32(max)).contains(&x);
+    let steady = |player: &Player| {
+      in_range(player.speed, MAX_SPEED)
+        && in_range(player.weight, MAX_WEIGHT)
+        && player.spigots.iter().all(|spigot| {
+          [spigot.pos, spigot.tail].iter().flatten().all(|&pos| on_board(&Point::from_fixed(pos)))
+        })
+    };
+    if !snapshot.players.iter().all(steady) {
+      return None;
+    }
+
+    // Merge the keys with placeholders first and only then put the real bodies in,
+    // because `Body::union` rebuilds the RTrees.
+    let mut bodies: UF<Body> = UF::new(keys);
+    for key in 0..keys {
+      bodies.union(snapshot.roots[key], key);
+    }
+    let roots = (0..keys).filter(|&key| snapshot.roots[key] == key);
+    for (key, body) in roots.zip(snapshot.bodies) {
+      *bodies.get_mut(key) = body;
+    }
+
+    let mut board = Board {
+      players: snapshot.players,
+      capturing: snapshot.capturing,
+      width: snapshot.width,
+      height: snapshot.height,
+      topology: snapshot.topology,
+      grid: snapshot.grid,
+      sharing: snapshot.sharing,
+      schedule: snapshot.schedule,
+      teams_hash: checksum::hash_teams(&snapshot.teams),
+      teams: snapshot.teams,
+      image: vec![],
+      owner: snapshot.owner,
+      bodies,
+      alt_image: vec![0; area],
+      heatmap: vec![],
+      replay: snapshot.replay,
+      checksums: snapshot.checksums,
+      rollback: Rollback::default(),
+    };
+    board.image = board.teams.iter().map(|&team| board.color(team)).collect();
+    Some(board)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn tampered(tamper: impl Fn(&mut Snapshot)) -> Option<Board> {
+    let mut board = Board::new(8, true);
+    board.place_rect(0, &Point::new(2, 2), 2, 2);
+    board.place_rect(1, &Point::new(4, 2), 2, 2);
+    board.spill_players(&[Some(Point::new(1, 1)), None], 4, 0);
+    let mut snapshot: Snapshot = bincode::deserialize(&board.save()).unwrap();
+    tamper(&mut snapshot);
+    Board::load(&bincode::serialize(&snapshot).unwrap())
+  }
+
+  fn tampered_bodies(tamper: impl Fn(&mut Body)) -> Option<Board> {
+    tampered(|snapshot| snapshot.bodies.iter_mut().for_each(&tamper))
+  }
+
+  #[test]
+  fn load_checks_the_bodies() {
+    assert!(tampered_bodies(|_| ()).is_some());
+    assert!(tampered_bodies(|body| {
+      body.libs.insert(Point::new(8, 0));
+    })
+    .is_none());
+    assert!(tampered_bodies(|body| body.stollen_libs.push((Point::new(0, 0), 1000))).is_none());
+    assert!(tampered_bodies(|body| body.stollen_libs.push((Point::new(-1, 0), 0))).is_none());
+  }
+
+  #[test]
+  fn load_checks_the_players() {
+    let off_board = Point::new(20, 3).as_fixed();
+    assert!(tampered(|snapshot| snapshot.players[0].spigots[0].pos = Some(off_board)).is_none());
+    assert!(tampered(|snapshot| snapshot.players[0].spigots[0].tail = Some(off_board)).is_none());
+    assert!(tampered(|snapshot| snapshot.players[1].speed = Fixed::from_int(3)).is_none());
+    assert!(tampered(|snapshot| snapshot.players[1].weight = Fixed::from_int(-1)).is_none());
+  }
+
+  #[test]
+  fn load_checks_the_size() {
+    assert!(tampered(|snapshot| {
+      snapshot.width = usize::MAX;
+      snapshot.height = 2;
+    })
+    .is_none());
+  }
+}
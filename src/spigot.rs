@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Fixed, Input, Point, SpillResult};
+
+/// How a player's flow is shared out when they're spilling from more than one
+/// spigot at once, eg. with two fingers on a touchscreen.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sharing {
+  /// The player spills the same amount however many spigots they use, taking
+  /// turns between them. This is the default.
+  Split,
+  /// Every spigot spills the full flow, so two fingers spill twice as fast. With
+  /// `set_ink` each one is charged for what it spills.
+  Each,
+}
+
+impl Sharing {
+  /// Which of a player's spigots spill on their turn, given the keys of the ones
+  /// that are still going this frame. `turn` counts their turns so far.
+  pub(crate) fn taking(self, keys: &[Option<usize>], turn: &mut usize) -> Vec<usize> {
+    let live: Vec<usize> = (0..keys.len()).filter(|&i| keys[i].is_some()).collect();
+    match self {
+      _ if live.is_empty() => vec![],
+      Sharing::Split => {
+        *turn += 1;
+        vec![live[(*turn - 1) % live.len()]]
+      }
+      Sharing::Each => live,
+    }
+  }
+}
+
+/// One place a player is spilling from. The spill point doesn't jump straight to
+/// where the player is aiming, `tail` chases the target and `pos` chases `tail`,
+/// at the player's speed.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Spigot {
+  pub(crate) pos: Option<[Fixed; 2]>,
+  pub(crate) tail: Option<[Fixed; 2]>,
+}
+
+impl Spigot {
+  /// Called once a frame. A spigot that's just been turned on starts right where
+  /// it's aimed.
+  pub(crate) fn aim(&mut self, target: Option<Point>) {
+    if self.pos.is_none() || target.is_none() {
+      self.pos = target.map(Point::as_fixed);
+      self.tail = self.pos;
+    }
+  }
+
+  /// Move a step of `vroom` towards `target`.
+  pub(crate) fn chase(&mut self, target: Point, vroom: Fixed) {
+    if let (Some(tail), Some(pos)) = (&mut self.tail, &mut self.pos) {
+      let target = target.as_fixed();
+      for axis in 0..2 {
+        tail[axis] = tail[axis] + (target[axis] - tail[axis]) * vroom;
+        pos[axis] = pos[axis] + (tail[axis] - pos[axis]) * vroom;
+      }
+    }
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  pub fn set_sharing(&mut self, sharing: Sharing) {
+    if sharing != self.sharing {
+      self.replay.push(Input::SetSharing(sharing));
+      self.sharing = sharing;
+    }
+  }
+
+  /// `spill_spigots` flattened for crossing the js-wasm barrier. Each entry is a
+  /// spigot belonging to `owners[i]`, at (xs[i], ys[i]) and on if active[i] != 0.
+  /// A player's spigots are numbered in the order they appear, so keep each finger
+  /// in the same place in the list from frame to frame, and send it inactive
+  /// rather than leaving it out when it lifts.
+  pub fn spill_touches(
+    &mut self,
+    owners: &[u8],
+    xs: &[i32],
+    ys: &[i32],
+    active: &[u8],
+    count: u32,
+    first: usize,
+  ) -> SpillResult {
+    let mut spigots: Vec<Vec<Option<Point>>> = vec![vec![]; self.players.len()];
+    for i in 0..owners.len() {
+      let pos = Point::new(xs[i] as isize, ys[i] as isize);
+      spigots[owners[i] as usize].push(if active[i] != 0 { Some(pos) } else { None });
+    }
+    self.spill_spigots(&spigots, count, first)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// Black's liquid after a few frames of spilling from `fingers`.
+  fn spilled(sharing: Sharing, fingers: &[Option<Point>]) -> isize {
+    let mut board = Board::new(32, true);
+    board.set_sharing(sharing);
+    for frame in 0..5 {
+      board.spill_spigots(&[fingers.to_vec(), vec![]], 4, frame % 2);
+    }
+    board.score().stone(0)
+  }
+
+  #[test]
+  fn split_shares_the_flow_and_each_multiplies_it() {
+    let one = [Some(Point::new(8, 8))];
+    let two = [Some(Point::new(8, 8)), Some(Point::new(24, 24))];
+    let alone = spilled(Sharing::Split, &one);
+    assert!(alone > 0);
+    assert_eq!(spilled(Sharing::Each, &one), alone);
+    assert_eq!(spilled(Sharing::Split, &two), alone);
+    assert_eq!(spilled(Sharing::Each, &two), 2 * alone);
+  }
+
+  #[test]
+  fn speeds_stay_in_range() {
+    let mut board = Board::new(16, true);
+    assert!(!board.set_player_speed(0, f32::INFINITY));
+    assert!(board.set_player_speed(0, 1e4));
+    assert!(board.set_player_speed(1, -1.));
+    for frame in 0..20 {
+      let target = Point::new(frame % 16, 3);
+      board.spill_players(&[Some(target), Some(Point::new(8, 12))], 4, frame as usize % 2);
+    }
+    assert!(board.score().stone(0) > 0);
+  }
+}
@@ -0,0 +1,114 @@
+use rstar::RTree;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Point, TreeParams};
+
+/// How the edges of the board connect.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+  /// The edges are the edges, like in Go.
+  Plane,
+  /// Opposite edges are glued together, so there are no edges at all.
+  Torus,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Liberties are worked out as liquid spreads, so this should be set before the
+  /// first `spill`. Returns false and leaves the topology alone if the grid
+  /// doesn't fit it, see `Grid::Hex`.
+  pub fn set_topology(&mut self, topology: Topology) -> bool {
+    if !self.grid.fits(topology, self.height) {
+      return false;
+    }
+    if topology != self.topology {
+      self.replay.push(super::Input::SetTopology(topology));
+      self.topology = topology;
+    }
+    true
+  }
+}
+
+impl Board {
+  /// The cells next to `pos`, going by the `Grid` and across the edges if we can.
+  /// This doesn't borrow the board, so it's fine to change cells while iterating.
+  #[inline]
+  pub(crate) fn neighbors(&self, pos: Point) -> impl Iterator<Item = Point> + Clone {
+    let (topology, width, height) = (self.topology, self.width, self.height);
+    self.grid.offsets(pos.y).iter().filter_map(move |&(dx, dy)| {
+      topology.wrap(Point { x: pos.x + dx, y: pos.y + dy }, width, height)
+    })
+  }
+}
+
+impl Topology {
+  /// `pos` if it's on a `width` by `height` board, or where it ends up after going
+  /// across the edges. None if it's off the board.
+  #[inline]
+  pub(crate) fn wrap(self, pos: Point, width: usize, height: usize) -> Option<Point> {
+    let (w, h) = (width as isize, height as isize);
+    match self {
+      Topology::Plane => ((0..w).contains(&pos.x) && (0..h).contains(&pos.y)).then_some(pos),
+      Topology::Torus => Some(Point { x: pos.x.rem_euclid(w), y: pos.y.rem_euclid(h) }),
+    }
+  }
+
+  /// The liberty in `libs` closest to `spigot` on a `width` by `height` board,
+  /// going across the edges if we can.
+  pub(crate) fn nearest_lib(
+    self,
+    libs: &RTree<Point, TreeParams>,
+    spigot: Point,
+    width: usize,
+    height: usize,
+  ) -> Option<Point> {
+    match self {
+      Topology::Plane => libs.nearest_neighbor(&spigot).copied(),
+      Topology::Torus => {
+        // The RTree only knows about flat distance, so we also search from copies
+        // of the spigot shifted by a board length, which find the liberties that are
+        // closer the other way around. We skip copies that are too far from the
+        // board to beat what we've already found.
+        let (w, h) = (width as isize, height as isize);
+        let mut best: Option<(isize, Point)> = None;
+        for dx in [0, -w, w] {
+          for dy in [0, -h, h] {
+            let query = Point { x: spigot.x + dx, y: spigot.y + dy };
+            let gap_x = (-query.x).max(query.x - (w - 1)).max(0);
+            let gap_y = (-query.y).max(query.y - (h - 1)).max(0);
+            if best.is_some_and(|(dist, _)| gap_x * gap_x + gap_y * gap_y >= dist) {
+              continue;
+            }
+            if let Some(&lib) = libs.nearest_neighbor(&query) {
+              let dist = (lib.x - query.x).pow(2) + (lib.y - query.y).pow(2);
+              if best.is_none_or(|(best_dist, _)| dist < best_dist) {
+                best = Some((dist, lib));
+              }
+            }
+          }
+        }
+        best.map(|(_, lib)| lib)
+      }
+    }
+  }
+
+  /// `nearest_lib`, and remove it from `libs`.
+  pub(crate) fn pop_nearest_lib(
+    self,
+    libs: &mut RTree<Point, TreeParams>,
+    spigot: Point,
+    width: usize,
+    height: usize,
+  ) -> Option<Point> {
+    match self {
+      Topology::Plane => libs.pop_nearest_neighbor(&spigot),
+      Topology::Torus => {
+        let lib = self.nearest_lib(libs, spigot, width, height)?;
+        libs.remove(&lib)
+      }
+    }
+  }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Write;
+
+use super::{MatchResult, Scoring};
+
+/// What a round robin of bot games adds up to. Record games in any order, from
+/// any number of threads, and the ratings come out the same.
+#[derive(Clone, Debug)]
+pub struct Tournament {
+  names: Vec<String>,
+  scoring: Scoring,
+  /// wins[i][j] is how many games i won against j, counting a draw as half.
+  wins: Vec<Vec<f64>>,
+  /// margins[i][j] is i's total minus j's, summed over their games.
+  margins: Vec<Vec<f64>>,
+  games: Vec<Vec<usize>>,
+  /// Games won by Black, White and nobody, to see if the rules favor a color.
+  colors: [usize; 3],
+  /// Black's total minus White's, summed over every game.
+  black_margin: f64,
+  unfinished: usize,
+}
+
+/// The rating of an average player.
+const AVERAGE: f64 = 1500.;
+
+impl Tournament {
+  /// Games are won and lost by `scoring`, with komi.
+  pub fn new(names: &[&str], scoring: Scoring) -> Self {
+    let n = names.len();
+    Tournament {
+      names: names.iter().map(|name| name.to_string()).collect(),
+      scoring,
+      wins: vec![vec![0.; n]; n],
+      margins: vec![vec![0.; n]; n],
+      games: vec![vec![0; n]; n],
+      colors: [0; 3],
+      black_margin: 0.,
+      unfinished: 0,
+    }
+  }
+
+  /// Count a game between entrants `black` and `white`.
+  pub fn record(&mut self, black: usize, white: usize, result: &MatchResult) {
+    let score = &result.score;
+    let margin = (score.total(self.scoring, 0) - score.total(self.scoring, 1)) as f64;
+    let (b_wins, color) = match score.winner(self.scoring) {
+      Some(0) => (1., 0),
+      Some(_) => (0., 1),
+      None => (0.5, 2),
+    };
+    for (us, them, won, margin) in
+      [(black, white, b_wins, margin), (white, black, 1. - b_wins, -margin)]
+    {
+      self.wins[us][them] += won;
+      self.margins[us][them] += margin;
+      self.games[us][them] += 1;
+    }
+    self.colors[color] += 1;
+    self.black_margin += margin;
+    self.unfinished += !result.finished as usize;
+  }
+
+  /// Elo ratings, in the same order as the names. They're fitted to all the games
+  /// at once (the Bradley-Terry model that Elo is built on) rather than updated
+  /// game by game, so they don't depend on the order games finished in. Everyone
+  /// gets one imaginary draw against each opponent, so a perfect record still has
+  /// a finite rating. The average is 1500, and 400 points is 10 to 1 odds.
+  pub fn ratings(&self) -> Vec<f64> {
+    let n = self.names.len();
+    let mut strength = vec![1.; n];
+    // Zermelo's iteration, which always converges for this model.
+    for _ in 0..1000 {
+      let next: Vec<f64> = (0..n)
+        .map(|i| {
+          let (mut won, mut expected) = (0., 0.);
+          for j in (0..n).filter(|&j| j != i) {
+            won += self.wins[i][j] + 0.5;
+            expected += (self.games[i][j] + 1) as f64 / (strength[i] + strength[j]);
+          }
+          if expected > 0. {
+            won / expected
+          } else {
+            1.
+          }
+        })
+        .collect();
+      // Only the ratios matter, so keep the geometric mean at 1.
+      let mean = next.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+      let next: Vec<f64> = next.iter().map(|s| s / mean.exp()).collect();
+      let change = next.iter().zip(&strength).map(|(a, b)| (a / b).ln().abs()).fold(0., f64::max);
+      strength = next;
+      if change < 1e-9 {
+        break;
+      }
+    }
+    strength.iter().map(|s| AVERAGE + 400. * s.log10()).collect()
+  }
+
+  /// The standings, best first, then everyone's score against everyone else, then
+  /// how Black did, as a plain text table.
+  pub fn table(&self) -> String {
+    let n = self.names.len();
+    let ratings = self.ratings();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| ratings[b].total_cmp(&ratings[a]));
+    let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(10);
+    let percent = |part: f64, whole: usize| 100. * part / whole.max(1) as f64;
+
+    let mut table = String::new();
+    let _ = writeln!(
+      table,
+      "{:<width$}  {:>6}  {:>6}  {:>7}  {:>6}  {:>8}",
+      "bot", "rating", "games", "won", "score", "margin"
+    );
+    for &i in &order {
+      let games: usize = self.games[i].iter().sum();
+      let wins: f64 = self.wins[i].iter().sum();
+      let margin: f64 = self.margins[i].iter().sum();
+      let _ = writeln!(
+        table,
+        "{:<width$}  {:>6.0}  {:>6}  {:>7.1}  {:>5.1}%  {:>+8.1}",
+        self.names[i],
+        ratings[i],
+        games,
+        wins,
+        percent(wins, games),
+        margin / games.max(1) as f64,
+      );
+    }
+
+    // Row against column: the row's share of the points, and its average margin.
+    let _ = write!(table, "\n{:<width$}", "");
+    for &j in &order {
+      let _ = write!(table, "  {:>width$}", self.names[j]);
+    }
+    for &i in &order {
+      let _ = write!(table, "\n{:<width$}", self.names[i]);
+      for &j in &order {
+        let cell = match self.games[i][j] {
+          _ if i == j => "-".to_string(),
+          0 => "".to_string(),
+          games => format!(
+            "{:.0}% {:+.0}",
+            percent(self.wins[i][j], games),
+            self.margins[i][j] / games as f64
+          ),
+        };
+        let _ = write!(table, "  {:>width$}", cell);
+      }
+    }
+
+    let total: usize = self.colors.iter().sum();
+    let [black, white, draws] = self.colors;
+    let _ = write!(
+      table,
+      "\n\nBlack won {} ({:.1}%), White won {} ({:.1}%), {} drawn, of {} games.\n\
+       Black's average margin: {:+.2}.",
+      black,
+      percent(black as f64, total),
+      white,
+      percent(white as f64, total),
+      draws,
+      total,
+      self.black_margin / total.max(1) as f64,
+    );
+    if self.unfinished > 0 {
+      let _ = write!(
+        table,
+        "\n{} games hit the frame limit and were scored as they stood.",
+        self.unfinished
+      );
+    }
+    table + "\n"
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{Board, Point};
+
+  /// A finished game that `winner` won by a cell of liquid.
+  fn won_by(winner: usize) -> MatchResult {
+    let mut board = Board::new(4, true);
+    board.place(winner, &[Point::new(0, 0)]);
+    MatchResult { score: board.score(), frames: 1, finished: true }
+  }
+
+  #[test]
+  fn ratings_follow_the_record() {
+    let mut tournament = Tournament::new(&["a", "b"], Scoring::Stone);
+    for _ in 0..3 {
+      tournament.record(0, 1, &won_by(0));
+    }
+    tournament.record(1, 0, &won_by(0));
+    // With the imaginary draw, a won 3.5 of 5 games, so they're 7 to 3 favorites.
+    let ratings = tournament.ratings();
+    let gap = 400. * (7f64 / 3.).log10();
+    assert!((ratings[0] - ratings[1] - gap).abs() < 1e-6, "{:?}", ratings);
+    assert!((ratings[0] + ratings[1] - 2. * AVERAGE).abs() < 1e-6, "{:?}", ratings);
+  }
+
+  #[test]
+  fn nobody_is_favored_without_games() {
+    let tournament = Tournament::new(&["a", "b", "c"], Scoring::China);
+    assert_eq!(tournament.ratings(), [AVERAGE; 3]);
+  }
+}
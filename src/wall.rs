@@ -0,0 +1,65 @@
+use union_find::UnionFind;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Input, Point, Team};
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Board {
+  /// Put a wall on an empty cell, or take one away if `wall` is false. Walls can
+  /// go up at any time, but not on top of liquid, so cells with liquid are left
+  /// alone. Returns whether the cell changed.
+  ///
+  /// A body walled in on every side dies right away, like any other body with no
+  /// liberties, but nobody gets it as prisoners.
+  pub fn set_wall(&mut self, pos: &Point, wall: bool) -> bool {
+    let pos = *pos;
+    let (from, to) = if wall { (Team::EMPTY, Team::WALL) } else { (Team::WALL, Team::EMPTY) };
+    if !(0..self.width as isize).contains(&pos.x)
+      || !(0..self.height as isize).contains(&pos.y)
+      || self.get_teams(pos) != from
+    {
+      return false;
+    }
+    self.replay.push(Input::SetWall { pos, wall });
+    self.set_team(self.get_idx(pos), to);
+
+    // The cell stops or starts being a liberty of the liquid around it.
+    for neighbor in self.neighbors(pos) {
+      if self.get_teams(neighbor).player().is_some() {
+        let body = self.bodies.get_mut(self.get_owner(neighbor));
+        if wall {
+          body.libs.remove(&pos);
+        } else {
+          body.insert(pos);
+        }
+      }
+    }
+    if wall {
+      for neighbor in self.neighbors(pos).collect::<Vec<_>>() {
+        if self.get_teams(neighbor).player().is_some() {
+          self.check_dead(self.get_owner(neighbor), Team::WALL);
+        }
+      }
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn walled_in_liquid_dies_without_prisoners() {
+    let mut board = Board::with_players(8, 8, true, 3);
+    board.place(0, &[Point::new(0, 0)]);
+    board.place(1, &[Point::new(3, 0)]);
+    board.set_wall(&Point::new(1, 0), true);
+    assert_eq!(board.get_teams(Point::new(0, 0)), Team(0));
+    board.set_wall(&Point::new(0, 1), true);
+    assert_eq!(board.get_teams(Point::new(0, 0)), Team::EMPTY);
+    assert_eq!((0..3).map(|player| board.prisoners(player)).collect::<Vec<_>>(), [0, 0, 0]);
+    assert_eq!(board.players[0].lost, 1);
+  }
+}